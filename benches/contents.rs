@@ -0,0 +1,146 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Compare loading the stacks and collections content in one pass
+//! with a query per stack / collection (N+1), on a large synthetic
+//! catalog.
+//!
+//! Run with `cargo bench --bench contents`.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use rusqlite::Connection;
+
+use c1::{Catalog, CoId};
+
+const STACKS: i64 = 200_000;
+const COLLECTIONS: i64 = 2_000;
+
+const SCHEMA: &str = "
+PRAGMA journal_mode=WAL;
+CREATE TABLE ZENTITIES (Z_ENT INTEGER PRIMARY KEY, ZNAME VARCHAR);
+CREATE TABLE ZVERSIONINFO (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZVERSION INTEGER);
+CREATE TABLE ZDOCUMENTCONTENT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZROOTCOLLECTION INTEGER);
+CREATE TABLE ZSTACK (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZCOLLECTION INTEGER,
+    ZPICKEDIMAGE INTEGER);
+CREATE INDEX ZSTACK_ZCOLLECTION_INDEX ON ZSTACK (ZCOLLECTION);
+CREATE TABLE ZSTACKIMAGELINK (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZINDEX INTEGER,
+    ZIMAGE INTEGER, ZSTACK INTEGER);
+CREATE INDEX ZSTACKIMAGELINK_ZSTACK_INDEX ON ZSTACKIMAGELINK (ZSTACK);
+CREATE TABLE ZCOLLECTION (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR,
+    ZPARENT INTEGER, ZFOLDERLOCATION INTEGER);
+INSERT INTO ZENTITIES VALUES (1, 'Stack'), (2, 'AlbumCollection'), (3, 'ProjectCollection');
+INSERT INTO ZVERSIONINFO VALUES (1, 4, 2300);
+INSERT INTO ZDOCUMENTCONTENT VALUES (1, 5, 1);
+INSERT INTO ZCOLLECTION VALUES (1, 3, NULL, NULL, NULL);
+";
+
+fn create_catalog(path: &Path) {
+    let mut conn = Connection::open(path).unwrap();
+    conn.execute_batch(SCHEMA).unwrap();
+    let tx = conn.transaction().unwrap();
+    {
+        let mut collection = tx
+            .prepare("INSERT INTO ZCOLLECTION VALUES (?1, 2, ?2, 1, NULL)")
+            .unwrap();
+        for id in 2..COLLECTIONS + 2 {
+            collection.execute((id, format!("Album {id}"))).unwrap();
+        }
+        let mut stack = tx
+            .prepare("INSERT INTO ZSTACK VALUES (?1, 1, ?2, ?1)")
+            .unwrap();
+        let mut link = tx
+            .prepare("INSERT INTO ZSTACKIMAGELINK VALUES (NULL, NULL, ?3, ?1, ?2)")
+            .unwrap();
+        for id in 1..STACKS + 1 {
+            stack.execute((id, id % COLLECTIONS + 2)).unwrap();
+            // Two images for every 10th stack.
+            link.execute((id, id, 0)).unwrap();
+            if id % 10 == 0 {
+                link.execute((id + STACKS, id, 1)).unwrap();
+            }
+        }
+    }
+    tx.commit().unwrap();
+}
+
+/// Load the content with one query per stack and per collection.
+fn load_n_plus_one(path: &Path) -> usize {
+    let conn = Connection::open(path).unwrap();
+    let mut count = 0;
+    let stacks = conn
+        .prepare("SELECT Z_PK FROM ZSTACK")
+        .unwrap()
+        .query_map([], |row| row.get::<usize, CoId>(0))
+        .unwrap()
+        .collect::<rusqlite::Result<Vec<CoId>>>()
+        .unwrap();
+    for stack in stacks {
+        let mut stmt = conn
+            .prepare("SELECT ZIMAGE FROM ZSTACKIMAGELINK WHERE ZSTACK=?1")
+            .unwrap();
+        count += stmt
+            .query_map([stack], |row| row.get::<usize, CoId>(0))
+            .unwrap()
+            .count();
+    }
+    for collection in 1..COLLECTIONS + 2 {
+        let mut stmt = conn
+            .prepare("SELECT Z_PK FROM ZSTACK WHERE ZCOLLECTION=?1")
+            .unwrap();
+        count += stmt
+            .query_map([collection], |row| row.get::<usize, CoId>(0))
+            .unwrap()
+            .count();
+    }
+    count
+}
+
+/// Load the content with the catalog.
+fn load_batch(path: &Path) -> usize {
+    let mut catalog = Catalog::new(path);
+    catalog.open().unwrap();
+    let stacks = catalog.load_stacks().unwrap();
+    let mut count = stacks
+        .iter()
+        .map(|stack| stack.content.as_ref().map(Vec::len).unwrap_or(0))
+        .sum::<usize>();
+    let collections = catalog.load_collections().unwrap();
+    count += collections
+        .iter()
+        .map(|collection| collection.content.as_ref().map(Vec::len).unwrap_or(0))
+        .sum::<usize>();
+    count
+}
+
+fn measure(name: &str, path: &Path, f: fn(&Path) -> usize) -> Duration {
+    const RUNS: u32 = 3;
+    let mut total = Duration::ZERO;
+    let mut count = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        count = f(path);
+        total += start.elapsed();
+    }
+    let average = total / RUNS;
+    println!("{name:>12}: {average:>10.2?} ({count} links)");
+    average
+}
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("Bench.cocatalogdb");
+    println!("Creating a catalog with {STACKS} stacks and {COLLECTIONS} collections...");
+    create_catalog(&path);
+
+    let n_plus_one = measure("N+1", &path, load_n_plus_one);
+    let batch = measure("batch", &path, load_batch);
+    println!(
+        "Speedup: {:.1}x",
+        n_plus_one.as_secs_f64() / batch.as_secs_f64()
+    );
+}
@@ -0,0 +1,113 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::collections::{BTreeMap, HashMap};
+
+use rusqlite::Connection;
+
+use super::schema::table_columns;
+use super::{CoId, LoadReport, Result};
+
+/// Split a Core Data join column or table name `Z_<entity><NAME>`
+/// into the entity id and the name.
+fn split_entity(name: &str) -> Option<(CoId, &str)> {
+    let rest = name.strip_prefix("Z_")?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+    let entity = rest[..digits].parse().ok()?;
+    Some((entity, &rest[digits..]))
+}
+
+/// The keywords assigned to images and variants, from the Core Data
+/// many-to-many join tables `Z_<entity>KEYWORDS`.
+#[derive(Debug, Default)]
+pub struct KeywordAssignments {
+    /// Image id -> keyword ids.
+    pub images: BTreeMap<CoId, Vec<CoId>>,
+    /// Variant id -> keyword ids.
+    pub variants: BTreeMap<CoId, Vec<CoId>>,
+}
+
+impl KeywordAssignments {
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty() && self.variants.is_empty()
+    }
+
+    /// Discover the join tables between `keyword_entity` and the
+    /// other entities, and load the Image and Variant ones. Rows with
+    /// a `NULL` are reported, with the owner id.
+    pub(crate) fn load(
+        conn: &Connection,
+        entities: &HashMap<CoId, String>,
+        keyword_entity: CoId,
+        report: &mut LoadReport,
+    ) -> Result<KeywordAssignments> {
+        let mut assignments = KeywordAssignments::default();
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name LIKE 'Z\\_%KEYWORDS' ESCAPE '\\'",
+        )?;
+        let tables = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        for table in tables {
+            let Some((owner, "KEYWORDS")) = split_entity(&table) else {
+                continue;
+            };
+            let owner_name = entities.get(&owner).map(String::as_str);
+            let target = match owner_name {
+                Some("Image") => &mut assignments.images,
+                Some("Variant") => &mut assignments.variants,
+                _ => {
+                    log::info!("Skipping keyword join table {} for {:?}", table, owner_name);
+                    continue;
+                }
+            };
+            let columns = table_columns(conn, &table)?;
+            let owner_column = columns
+                .iter()
+                .find(|column| matches!(split_entity(column), Some((ent, _)) if ent == owner));
+            let keyword_column = columns.iter().find(|column| {
+                matches!(split_entity(column), Some((ent, "KEYWORDS")) if ent == keyword_entity)
+            });
+            let (Some(owner_column), Some(keyword_column)) = (owner_column, keyword_column) else {
+                log::warn!("Unexpected columns in keyword join table {}", table);
+                continue;
+            };
+            let mut stmt = conn.prepare(&format!(
+                "SELECT \"{}\", \"{}\" FROM \"{}\"",
+                owner_column, keyword_column, table
+            ))?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                match (row.get(0), row.get(1)) {
+                    (Ok(owner), Ok(keyword)) => target.entry(owner).or_default().push(keyword),
+                    (Err(err), _) | (_, Err(err)) => {
+                        let owner = row
+                            .get::<usize, Option<CoId>>(0)
+                            .ok()
+                            .flatten()
+                            .unwrap_or(0);
+                        report.skip(&table, owner, row, err)?
+                    }
+                }
+            }
+        }
+
+        Ok(assignments)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::split_entity;
+
+    #[test]
+    fn test_split_entity() {
+        assert_eq!(split_entity("Z_14KEYWORDS"), Some((14, "KEYWORDS")));
+        assert_eq!(split_entity("Z_3VARIANTS"), Some((3, "VARIANTS")));
+        assert_eq!(split_entity("Z_PK"), None);
+        assert_eq!(split_entity("ZKEYWORDS"), None);
+    }
+}
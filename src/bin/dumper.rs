@@ -7,12 +7,14 @@
 extern crate c1;
 
 use std::collections::BTreeMap;
-use std::iter::FromIterator;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{Parser, Subcommand};
 
-use c1::{Catalog, CatalogVersion, CoId, Collection, Folder, Image, Keyword, KeywordTree, Stack};
+use c1::{
+    Catalog, CoId, Collection, Folder, Image, Keyword, KeywordTree, LoadMode, Session, Stack,
+    Variant,
+};
 
 #[derive(Debug, Parser)]
 #[command(version)]
@@ -27,13 +29,15 @@ enum Command {
     Dump(DumpArgs),
     /// List the files / folders in the catalog.
     List(ListArgs),
+    /// Show the collection tree.
+    Tree(TreeArgs),
     /// Audit (Unimplemented)
     Audit,
 }
 
 #[derive(Debug, Parser)]
 struct DumpArgs {
-    /// Path to the catalog.
+    /// Path to the catalog or session.
     path: PathBuf,
     /// Dump all.
     #[arg(long)]
@@ -53,11 +57,17 @@ struct DumpArgs {
     /// Dump keywords.
     #[arg(long)]
     keywords: bool,
+    /// Dump variants.
+    #[arg(long)]
+    variants: bool,
+    /// Fail on malformed rows instead of skipping them.
+    #[arg(long)]
+    strict: bool,
 }
 
 #[derive(Debug, Parser)]
 struct ListArgs {
-    /// Path to the catalog.
+    /// Path to the catalog or session.
     path: PathBuf,
     /// List only directory.
     #[arg(short)]
@@ -67,32 +77,66 @@ struct ListArgs {
     sort: bool,
 }
 
+#[derive(Debug, Parser)]
+struct TreeArgs {
+    /// Path to the catalog or session.
+    path: PathBuf,
+    /// Print the full path of the collections.
+    #[arg(short)]
+    paths: bool,
+}
+
 fn main() -> c1::Result<()> {
+    env_logger::init();
     let args = Args::parse();
 
     match args.command {
         Command::List(args) => process_list(&args),
         Command::Dump(args) => process_dump(&args),
+        Command::Tree(args) => process_tree(&args),
         Command::Audit => process_audit(&args),
     }
 }
 
-fn process_list(args: &ListArgs) -> c1::Result<()> {
-    let mut catalog = Catalog::new(&args.path);
-    catalog.open()?;
+/// Open the catalog or the session at `path`. If it is in use, open
+/// a snapshot.
+fn open_catalog(path: &Path) -> c1::Result<Catalog> {
+    let (mut catalog, result) = if Session::is_session(path) {
+        let mut session = Session::new(path);
+        let result = session.open();
+        (session.into_catalog(), result)
+    } else {
+        let mut catalog = Catalog::new(path);
+        let result = catalog.open();
+        (catalog, result)
+    };
+    if let Err(c1::Error::CatalogInUse) = result {
+        eprintln!("Catalog in use, using a snapshot.");
+        catalog.open_snapshot()?;
+    } else {
+        result?;
+    }
 
-    // XXX this is stupid everything fails if this isn't called.
-    catalog.load_version()?;
-    let folders = catalog.load_folders();
+    Ok(catalog)
+}
 
-    let resolved_folders = BTreeMap::from_iter(folders.iter().map(|folder| {
+fn process_list(args: &ListArgs) -> c1::Result<()> {
+    let catalog = open_catalog(&args.path)?;
+
+    let mut resolved_folders = BTreeMap::new();
+    let mut folders = catalog.folders_iter()?;
+    for folder in &mut folders {
+        let folder = folder?;
         let resolved = if folder.is_relative {
             format!("./{}", folder.path_from_root)
         } else {
             format!("{}{}", folder.root_folder, folder.path_from_root)
         };
-        (folder.id(), resolved)
-    }));
+        resolved_folders.insert(folder.id(), resolved);
+    }
+    for warning in folders.warnings() {
+        eprintln!("Skipped: {warning}");
+    }
     if args.dirs {
         let mut dirs = resolved_folders.values().collect::<Vec<&String>>();
         if args.sort {
@@ -103,78 +147,129 @@ fn process_list(args: &ListArgs) -> c1::Result<()> {
         return Ok(());
     }
 
-    let images = catalog.load_images();
-    let mut image_files = images
-        .iter()
-        .filter_map(|image| {
-            resolved_folders
-                .get(&image.folder)
-                .map(|folder| format!("{}/{}", folder, image.file_name))
-        })
-        .collect::<Vec<String>>();
+    // Only keep the file names when sorting.
+    let mut image_files = vec![];
+    let mut images = catalog.images_iter()?;
+    for image in &mut images {
+        let image = image?;
+        if let Some(folder) = resolved_folders.get(&image.folder) {
+            let file = format!("{}/{}", folder, image.file_name);
+            if args.sort {
+                image_files.push(file);
+            } else {
+                println!("{file}");
+            }
+        }
+    }
+    for warning in images.warnings() {
+        eprintln!("Skipped: {warning}");
+    }
     if args.sort {
         image_files.sort_unstable();
+        image_files.iter().for_each(|file| println!("{file}"));
     }
-    image_files.iter().for_each(|file| println!("{file}"));
 
     Ok(())
 }
 
 fn process_dump(args: &DumpArgs) -> c1::Result<()> {
-    let mut catalog = Catalog::new(&args.path);
-    catalog.open()?;
+    let mut catalog = open_catalog(&args.path)?;
+    if args.strict {
+        catalog.set_load_mode(LoadMode::Strict);
+    }
 
-    catalog.load_version()?;
     println!("Catalog:");
     println!(
         "\tVersion: {} ({:?})",
         catalog.version, catalog.catalog_version
     );
+    if catalog.schema_version != catalog.catalog_version {
+        println!("\tClosest schema: {:?}", catalog.schema_version);
+    }
     println!("\tRoot collection id: {}", catalog.root_collection_id);
 
-    match catalog.catalog_version {
-        CatalogVersion::Co1210 | CatalogVersion::Co1200 | CatalogVersion::Co1106 => {}
-        _ => {
-            println!("Unsupported catalog version");
-            return Err(c1::Error::UnsupportedVersion);
-        }
+    if !catalog.schema_version.is_supported() {
+        println!("Unsupported catalog version");
+        return Err(c1::Error::UnsupportedVersion);
     }
 
-    {
-        let keywordtree = catalog.load_keywords_tree();
-        let keywords = catalog.load_keywords();
-
-        if args.all || args.keywords {
-            dump_keywords(0, keywords, &keywordtree);
+    match catalog.load_info() {
+        Ok(info) => {
+            println!("\tHistory:");
+            for version in &info.history {
+                println!(
+                    "\t\t{} {} (compatible {})",
+                    version.version,
+                    version.author.as_deref().unwrap_or(""),
+                    version
+                        .compatible_version
+                        .map(|v| v.to_string())
+                        .unwrap_or_default(),
+                );
+            }
         }
+        Err(err) => eprintln!("Can't load the catalog info: {err}"),
     }
 
-    {
-        let folders = catalog.load_folders();
-        if args.all || args.folders {
-            dump_folders(folders);
-        }
+    let data = catalog.load_all()?;
+    if args.all || args.keywords {
+        dump_keywords(0, data.keywords(), data.keyword_tree());
+    }
+    if args.all || args.folders {
+        dump_folders(data.folders());
+    }
+    if args.all || args.images {
+        dump_images(data.images());
+    }
+    if args.all || args.stacks {
+        dump_stacks(data.stacks());
+    }
+    if args.all || args.collections {
+        dump_collections(data.collections());
+    }
+    if args.all || args.variants {
+        dump_variants(data.variants());
     }
 
-    {
-        let images = catalog.load_images();
-        if args.all || args.images {
-            dump_images(images);
-        }
+    for warning in catalog.warnings() {
+        eprintln!("Skipped: {warning}");
     }
 
-    {
-        let stacks = catalog.load_stacks();
-        if args.all || args.stacks {
-            dump_stacks(stacks);
+    Ok(())
+}
+
+fn process_tree(args: &TreeArgs) -> c1::Result<()> {
+    let mut catalog = open_catalog(&args.path)?;
+    let tree = catalog.load_collection_tree()?;
+    let collections = catalog.load_collections()?;
+    let collections = BTreeMap::from_iter(
+        collections
+            .iter()
+            .map(|collection| (collection.id, collection)),
+    );
+
+    for id in tree.descendants(tree.root()) {
+        let Some(collection) = collections.get(&id) else {
+            continue;
+        };
+        let trash = if tree.trash() == Some(id) {
+            " (trash)"
+        } else {
+            ""
+        };
+        if args.paths {
+            println!("{:>7} {}{}", id, tree.path(id), trash);
+        } else {
+            let indent = "  ".repeat(tree.ancestors(id).len().saturating_sub(1));
+            println!(
+                "{:>7} {}{}{}",
+                id, indent, collection.collection_type, trash
+            );
         }
     }
 
-    {
-        let collections = catalog.load_collections();
-        if args.all || args.collections {
-            dump_collections(collections);
-        }
+    for warning in catalog.warnings() {
+        eprintln!("Skipped: {warning}");
     }
 
     Ok(())
@@ -234,16 +329,28 @@ fn dump_folders(folders: &[Folder]) {
 
 fn dump_images(images: &[Image]) {
     println!("Images");
-    println!("+---------+--------------------------------------+----------+--------+----+--------------+");
-    println!("| id      | uuid                                 | DisplayN | format | cl | file name    |");
-    println!("+---------+--------------------------------------+----------+--------+----+--------------+");
+    println!("+---------+--------------------------------------+----------+--------+----+--------------+--------");
+    println!("| id      | uuid                                 | DisplayN | format | cl | file name    | camera");
+    println!("+---------+--------------------------------------+----------+--------+----+--------------+--------");
     for image in images {
+        let camera = [&image.capture.camera_make, &image.capture.camera_model]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
         println!(
-            "| {:>7} | {} | {:>8} | {:<6} | {:<2} | {} |",
-            image.id, image.uuid, image.display_name, image.format, image.class, image.file_name,
+            "| {:>7} | {} | {:>8} | {:<6} | {:<2} | {} | {}",
+            image.id,
+            image.uuid,
+            image.display_name,
+            image.format,
+            image.class,
+            image.file_name,
+            camera,
         );
     }
-    println!("+---------+--------------------------------------+---------+---------+----+--------------+");
+    println!("+---------+--------------------------------------+---------+---------+----+--------------+--------");
 }
 
 fn dump_stacks(stacks: &[Stack]) {
@@ -284,6 +391,40 @@ fn dump_collections(collections: &[Collection]) {
     println!("+---------+------------------------------------------+---------+-------");
 }
 
+fn dump_variants(variants: &[Variant]) {
+    println!("Variants");
+    println!(
+        "+---------+--------------------------------------+---------+--------+--------+--------+----------------"
+    );
+    println!(
+        "| id      | uuid                                 | image   | engine | rating | color  | product"
+    );
+    println!(
+        "+---------+--------------------------------------+---------+--------+--------+--------+----------------"
+    );
+    for variant in variants {
+        println!(
+            "| {:>7} | {} | {:>7} | {:>6} | {:>6} | {:<6} | {}",
+            variant.id,
+            variant.uuid,
+            variant.image,
+            variant
+                .processing_engine
+                .map(|engine| engine.to_string())
+                .unwrap_or_default(),
+            variant
+                .xmp_rating()
+                .map(|rating| rating.to_string())
+                .unwrap_or_default(),
+            variant.color_tag.unwrap_or_default(),
+            variant.product_id.as_deref().unwrap_or(""),
+        );
+    }
+    println!(
+        "+---------+--------------------------------------+---------+--------+--------+--------+----------------"
+    );
+}
+
 fn process_audit(_: &Args) -> c1::Result<()> {
     Err(c1::Error::Unimplemented)
 }
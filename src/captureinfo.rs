@@ -0,0 +1,106 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use super::CoDate;
+
+/// The capture metadata of an image, from the EXIF columns of ZIMAGE.
+/// All of them are optional.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CaptureInfo {
+    /// The capture date (ZEXP_CAPTUREDATE).
+    pub date: Option<CoDate>,
+    /// The capture time zone, like "+02:00" (ZEXP_CAPTURETIMEZONE).
+    pub time_zone: Option<String>,
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens: Option<String>,
+    /// Focal length in mm.
+    pub focal_length: Option<f64>,
+    /// Aperture as a f-number.
+    pub aperture: Option<f64>,
+    /// Shutter speed in seconds.
+    pub shutter_speed: Option<f64>,
+    pub iso: Option<i64>,
+    /// Exposure compensation in EV.
+    pub exposure_compensation: Option<f64>,
+    /// EXIF orientation, 1 to 8.
+    pub orientation: Option<i32>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    /// File size in bytes.
+    pub file_size: Option<i64>,
+}
+
+impl CaptureInfo {
+    /// Read the capture info from `row`, the columns starting at `first`
+    /// in the order of the IMAGES mapping. A value of the wrong type is
+    /// `None`.
+    pub(crate) fn from_row(row: &rusqlite::Row, first: usize) -> CaptureInfo {
+        CaptureInfo {
+            date: row.get(first).ok().flatten(),
+            time_zone: row.get(first + 1).ok().flatten(),
+            camera_make: row.get(first + 2).ok().flatten(),
+            camera_model: row.get(first + 3).ok().flatten(),
+            lens: row.get(first + 4).ok().flatten(),
+            focal_length: row.get(first + 5).ok().flatten(),
+            aperture: row.get(first + 6).ok().flatten(),
+            shutter_speed: row.get(first + 7).ok().flatten(),
+            iso: row.get(first + 8).ok().flatten(),
+            exposure_compensation: row.get(first + 9).ok().flatten(),
+            orientation: row.get(first + 10).ok().flatten(),
+            width: row.get(first + 11).ok().flatten(),
+            height: row.get(first + 12).ok().flatten(),
+            file_size: row.get(first + 13).ok().flatten(),
+        }
+    }
+
+    /// The shutter speed for display: "1/250" below one second,
+    /// "2.5\"" otherwise.
+    pub fn shutter_speed_display(&self) -> Option<String> {
+        let speed = self.shutter_speed.filter(|speed| *speed > 0.0)?;
+        if speed < 1.0 {
+            Some(format!("1/{}", (1.0 / speed).round()))
+        } else {
+            Some(format!("{speed}\""))
+        }
+    }
+
+    /// The dimensions, swapped if the orientation is rotated 90°.
+    pub fn oriented_dimensions(&self) -> Option<(i64, i64)> {
+        let (width, height) = (self.width?, self.height?);
+        match self.orientation {
+            Some(5..=8) => Some((height, width)),
+            _ => Some((width, height)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CaptureInfo;
+
+    #[test]
+    fn test_capture_info() {
+        let mut info = CaptureInfo {
+            shutter_speed: Some(0.004),
+            width: Some(6000),
+            height: Some(4000),
+            ..Default::default()
+        };
+        assert_eq!(info.shutter_speed_display().as_deref(), Some("1/250"));
+        assert_eq!(info.oriented_dimensions(), Some((6000, 4000)));
+
+        info.shutter_speed = Some(2.5);
+        info.orientation = Some(6);
+        assert_eq!(info.shutter_speed_display().as_deref(), Some("2.5\""));
+        assert_eq!(info.oriented_dimensions(), Some((4000, 6000)));
+
+        info.shutter_speed = Some(0.0);
+        info.height = None;
+        assert!(info.shutter_speed_display().is_none());
+        assert!(info.oriented_dimensions().is_none());
+    }
+}
@@ -5,27 +5,98 @@
 */
 
 use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-use rusqlite::{params, Connection};
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, MAIN_DB};
 
-use super::{CoId, Error, Result};
-use super::{Collection, Folder, Folders, Image, Keyword, KeywordTree, Stack};
+use super::data::Content;
+use super::iter::{FromRow, ObjectIter};
+use super::schema::{table_columns, Query, Schema};
+use super::{
+    CatalogData, CatalogInfo, CoId, Error, KeywordAssignments, LoadMode, LoadReport, LoadWarning,
+    Result,
+};
+use super::{Collection, CollectionTree, Folder, Folders, Image, Keyword, KeywordTree};
+use super::{Stack, StoreMetadata, Variant, VariantMetadata};
 
-const DB_FILENAME: &str = "Capture One Catalog.cocatalogdb";
+pub(crate) const DB_FILENAME: &str = "Capture One Catalog.cocatalogdb";
+const DB_EXTENSION: &str = ".cocatalogdb";
+const ARCHIVE_EXTENSION: &str = "zip";
+/// The files where SQLite keeps the changes not in the database yet.
+const JOURNAL_SUFFIXES: [&str; 2] = ["-wal", "-journal"];
+/// How long `open_snapshot` waits while the catalog is written.
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(30);
 
-#[derive(Debug, Default, PartialEq)]
+/// The catalog schema version, from `ZVERSIONINFO.ZVERSION`.
+/// Ordered from the oldest to the newest.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub enum CatalogVersion {
     #[default]
     Unknown,
     Co1106,
     Co1200,
     Co1210,
+    Co1300,
+    Co2000,
+    Co2100,
+    Co2200,
+    Co2300,
+}
+
+impl CatalogVersion {
+    const KNOWN: [CatalogVersion; 8] = [
+        CatalogVersion::Co1106,
+        CatalogVersion::Co1200,
+        CatalogVersion::Co1210,
+        CatalogVersion::Co1300,
+        CatalogVersion::Co2000,
+        CatalogVersion::Co2100,
+        CatalogVersion::Co2200,
+        CatalogVersion::Co2300,
+    ];
+
+    /// Whether the version is one we know how to read.
+    pub fn is_supported(&self) -> bool {
+        *self != CatalogVersion::Unknown
+    }
+
+    /// The `ZVERSION` value. 0 if unknown.
+    pub fn number(&self) -> i32 {
+        match *self {
+            CatalogVersion::Unknown => 0,
+            CatalogVersion::Co1106 => 1106,
+            CatalogVersion::Co1200 => 1200,
+            CatalogVersion::Co1210 => 1210,
+            CatalogVersion::Co1300 => 1300,
+            CatalogVersion::Co2000 => 2000,
+            CatalogVersion::Co2100 => 2100,
+            CatalogVersion::Co2200 => 2200,
+            CatalogVersion::Co2300 => 2300,
+        }
+    }
+
+    /// The newest known version not newer than `val`.
+    pub fn closest(val: i32) -> CatalogVersion {
+        Self::KNOWN
+            .iter()
+            .rev()
+            .find(|version| version.number() <= val)
+            .copied()
+            .unwrap_or_default()
+    }
 }
 
 impl From<i32> for CatalogVersion {
     fn from(val: i32) -> Self {
         match val {
+            2300 => CatalogVersion::Co2300,
+            2200 => CatalogVersion::Co2200,
+            2100 => CatalogVersion::Co2100,
+            2000 => CatalogVersion::Co2000,
+            1300 => CatalogVersion::Co1300,
             1210 => CatalogVersion::Co1210,
             1200 => CatalogVersion::Co1200,
             1106 => CatalogVersion::Co1106,
@@ -41,6 +112,10 @@ pub struct Catalog {
     db_only: bool,
     pub version: i32,
     pub catalog_version: CatalogVersion,
+    /// The known schema version used to read the catalog. Same as
+    /// `catalog_version` unless it is `Unknown`, in which case it is
+    /// the closest match.
+    pub schema_version: CatalogVersion,
     pub root_collection_id: CoId,
 
     /// The keywords, mapped in the local `CoId`
@@ -53,9 +128,19 @@ pub struct Catalog {
     images: Vec<Image>,
     /// Stacks
     stacks: Vec<Stack>,
+    /// Variants
+    variants: Vec<Variant>,
+    /// Variant metadata
+    variant_metadata: Vec<VariantMetadata>,
+    /// Keywords of the images and variants
+    keyword_assignments: KeywordAssignments,
     /// The entities
     entities_id_to_name: HashMap<CoId, String>,
     entities_name_to_id: HashMap<String, CoId>,
+    /// The schema, for the version
+    schema: Schema,
+    /// The load warnings
+    report: LoadReport,
     /// The sqlite connection to the catalog
     dbconn: Option<Connection>,
 }
@@ -68,131 +153,1152 @@ impl Catalog {
         }
     }
 
-    pub fn open(&mut self) -> Result<()> {
+    /// The path to the database file.
+    pub fn db_path(&self) -> PathBuf {
         let mut db_path = self.path.clone();
-        self.db_only = !self.path.is_dir();
-        if !self.db_only {
+        if self.path.is_dir() {
             db_path.push(DB_FILENAME);
         }
-        let conn_attempt = Connection::open(&db_path);
-        self.dbconn = Some(conn_attempt?);
+        db_path
+    }
 
-        Ok(())
+    /// Whether the catalog is in use, likely by Capture One, or has
+    /// changes not written to the database file yet.
+    ///
+    /// This is a heuristic on the files SQLite keeps next to the
+    /// database: a non empty `-wal` has changes not checkpointed, a
+    /// non empty `-journal` a transaction in progress. An empty or
+    /// missing `-wal` is idle, and `-shm` alone is ignored. Locks
+    /// aren't checked, as probing them would create these files.
+    pub fn is_in_use(&self) -> bool {
+        let db_path = self.db_path();
+        JOURNAL_SUFFIXES.iter().any(|suffix| {
+            std::fs::metadata(with_suffix(&db_path, suffix))
+                .map(|metadata| metadata.len() > 0)
+                .unwrap_or(false)
+        })
     }
 
-    pub fn load_version(&mut self) -> Result<()> {
-        if let Some(conn) = self.dbconn.as_ref() {
-            if let Ok(mut stmt) =
-                conn.prepare("SELECT ZVERSION FROM ZVERSIONINFO ORDER BY Z_PK DESC")
-            {
-                let mut rows = stmt.query(params![])?;
-                if let Ok(Some(row)) = rows.next() {
-                    self.version = row.get(0)?;
-                    self.catalog_version = CatalogVersion::from(self.version);
-                }
+    /// Open the catalog strictly read-only. The database is opened
+    /// immutable, so SQLite doesn't lock, create journal files or
+    /// checkpoint.
+    ///
+    /// Return `Error::CatalogInUse` if the catalog is in use since
+    /// the content can't be trusted.
+    pub fn open(&mut self) -> Result<()> {
+        self.db_only = !self.path.is_dir();
+        if self.is_archive() {
+            return self.open_archive();
+        }
+        if self.is_in_use() {
+            return Err(Error::CatalogInUse);
+        }
+        let uri = format!("file:{}?mode=ro&immutable=1", uri_path(&self.db_path()));
+        let conn_attempt = Connection::open_with_flags(
+            uri,
+            OpenFlags::SQLITE_OPEN_READ_ONLY
+                | OpenFlags::SQLITE_OPEN_URI
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        );
+        self.set_connection(conn_attempt?)
+    }
+
+    /// Whether the catalog is a zip backup archive.
+    pub fn is_archive(&self) -> bool {
+        self.path.is_file()
+            && self
+                .path
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case(ARCHIVE_EXTENSION))
+                .unwrap_or(false)
+    }
+
+    /// Open the catalog from the zip backup archive. The database is
+    /// decompressed into memory, nothing is written on disk.
+    fn open_archive(&mut self) -> Result<()> {
+        let file = std::fs::File::open(&self.path)?;
+        let mut archive = zip::ZipArchive::new(file)?;
+        let names = archive
+            .file_names()
+            .filter(|name| name.ends_with(DB_EXTENSION))
+            .map(String::from)
+            .collect::<Vec<String>>();
+        let name = names
+            .iter()
+            .find(|name| name.ends_with(DB_FILENAME))
+            .or_else(|| names.first())
+            .ok_or(Error::NoDatabase)?;
+        let mut entry = archive.by_name(name)?;
+        let size = entry.size() as usize;
+
+        let mut header = [0_u8; 100];
+        entry.read_exact(&mut header)?;
+        // The in-memory database doesn't support WAL, mark the file
+        // as using the rollback journal.
+        if header[18] == 2 && header[19] == 2 {
+            header[18] = 1;
+            header[19] = 1;
+        }
+        let mut conn = Connection::open_in_memory()?;
+        conn.deserialize_read_exact(
+            MAIN_DB,
+            std::io::Read::chain(&header[..], entry),
+            size,
+            true,
+        )?;
+        self.set_connection(conn)
+    }
+
+    /// Open a snapshot of the catalog: the database is copied into
+    /// memory using the SQLite backup API, including the content of
+    /// the WAL not yet checkpointed. Loading is done from the copy,
+    /// that doesn't change if the catalog is modified.
+    ///
+    /// To use when the catalog is in use. Nothing is written in the
+    /// catalog: if it is idle the database is opened immutable,
+    /// otherwise the database and its journal are first copied to a
+    /// temporary directory. Return `Error::CatalogInUse` if the
+    /// catalog keeps changing during the copy.
+    pub fn open_snapshot(&mut self) -> Result<()> {
+        self.open_snapshot_timeout(SNAPSHOT_TIMEOUT)
+    }
+
+    /// Open a snapshot of the catalog, retrying the copy at most for
+    /// `timeout` while it is written. See `open_snapshot()`.
+    pub fn open_snapshot_timeout(&mut self, timeout: Duration) -> Result<()> {
+        self.db_only = !self.path.is_dir();
+        if self.is_archive() {
+            return self.open_archive();
+        }
+        // The copy is removed once the backup is done.
+        let (source, _copy_dir) = if self.is_in_use() {
+            let dir = tempfile::tempdir()?;
+            let copy = copy_database(&self.db_path(), dir.path(), timeout)?;
+            // Not read-only, to recover the WAL of the copy.
+            (Connection::open(copy)?, Some(dir))
+        } else {
+            let uri = format!("file:{}?mode=ro&immutable=1", uri_path(&self.db_path()));
+            let conn = Connection::open_with_flags(
+                uri,
+                OpenFlags::SQLITE_OPEN_READ_ONLY
+                    | OpenFlags::SQLITE_OPEN_URI
+                    | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+            )?;
+            (conn, None)
+        };
+        let mut conn = Connection::open_in_memory()?;
+        // Nothing else uses the source, the copy is done in one step.
+        if Backup::new(&source, &mut conn)?.step(-1)? != StepResult::Done {
+            return Err(Error::CatalogInUse);
+        }
+        drop(source);
+        self.set_connection(conn)
+    }
+
+    /// Use `conn` for the catalog and detect the version and the
+    /// entities, so that the catalog is ready to load.
+    fn set_connection(&mut self, conn: Connection) -> Result<()> {
+        self.dbconn = Some(conn);
+        self.entities_id_to_name.clear();
+        self.entities_name_to_id.clear();
+        self.load_version()
+    }
+
+    /// Detect the version. Called when opening.
+    fn load_version(&mut self) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+        // Without ZVERSIONINFO the metadata is used.
+        if !table_columns(conn, "ZVERSIONINFO")?.is_empty() {
+            let version = conn
+                .query_row(
+                    "SELECT ZVERSION FROM ZVERSIONINFO ORDER BY Z_PK DESC",
+                    params![],
+                    |row| row.get::<usize, Option<i32>>(0),
+                )
+                .optional()?
+                .flatten();
+            if let Some(version) = version {
+                self.version = version;
+                self.catalog_version = CatalogVersion::from(self.version);
             }
-            if self.catalog_version != CatalogVersion::Unknown {
-                if let Ok(mut stmt) = conn.prepare("SELECT Z_ENT, ZNAME FROM ZENTITIES") {
-                    let mut rows = stmt.query(params![])?;
-                    while let Ok(Some(row)) = rows.next() {
-                        let ent: CoId = row.get(0)?;
-                        let name: String = row.get(1)?;
-                        self.entities_id_to_name.insert(ent, name.clone());
-                        self.entities_name_to_id.insert(name, ent);
-                    }
-                }
-                if let Ok(mut stmt) = conn.prepare("SELECT ZROOTCOLLECTION FROM ZDOCUMENTCONTENT") {
-                    let mut rows = stmt.query(params![])?;
-                    if let Ok(Some(row)) = rows.next() {
-                        self.root_collection_id = row.get(0)?;
-                    }
-                }
+        }
+        self.schema_version = self.catalog_version;
+        if self.schema_version == CatalogVersion::Unknown {
+            self.schema_version = CatalogVersion::closest(self.version);
+        }
+        if self.schema_version == CatalogVersion::Unknown {
+            if let Ok(metadata) = self.load_metadata() {
+                self.schema_version = metadata.closest_version();
+            }
+        }
+        if self.schema_version != CatalogVersion::Unknown {
+            self.schema = Schema::new(conn)?;
+            let mut stmt = conn.prepare(&self.schema.query(Query::Entities)?)?;
+            let mut rows = stmt.query(params![])?;
+            while let Some(row) = rows.next()? {
+                let ent: CoId = row.get(0)?;
+                let name: String = row.get(1)?;
+                self.entities_id_to_name.insert(ent, name.clone());
+                self.entities_name_to_id.insert(name, ent);
+            }
+            let mut stmt = conn.prepare(&self.schema.query(Query::DocumentContent)?)?;
+            let mut rows = stmt.query(params![])?;
+            if let Some(row) = rows.next()? {
+                self.root_collection_id = row.get::<usize, Option<CoId>>(0)?.unwrap_or(0);
             }
-            Ok(())
-        } else {
-            Err(Error::NoDatabase)
         }
+        Ok(())
     }
 
-    pub fn load_keywords_tree(&mut self) -> KeywordTree {
-        let keywords = self.load_keywords();
+    /// Check the catalog can be loaded.
+    fn check_loadable(&self) -> Result<()> {
+        if self.dbconn.is_none() {
+            return Err(Error::NoDatabase);
+        }
+        if !self.schema_version.is_supported() {
+            return Err(Error::UnsupportedVersion);
+        }
+        Ok(())
+    }
+
+    /// Get the entity id for `name`.
+    fn entity(&self, name: &str) -> Result<CoId> {
+        self.check_loadable()?;
+        self.entities_name_to_id
+            .get(name)
+            .copied()
+            .ok_or_else(|| Error::MissingEntity(name.to_string()))
+    }
+
+    /// Load the catalog information, including the version history.
+    pub fn load_info(&self) -> Result<CatalogInfo> {
+        self.check_loadable()?;
+        let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+        CatalogInfo::load(conn, &self.schema)
+    }
+
+    /// Load the Core Data store metadata from `Z_METADATA`.
+    pub fn load_metadata(&self) -> Result<StoreMetadata> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+        let (uuid, plist) =
+            conn.query_row("SELECT Z_UUID, Z_PLIST FROM Z_METADATA", [], |row| {
+                Ok((
+                    row.get::<usize, Option<String>>(0)?,
+                    row.get::<usize, Vec<u8>>(1)?,
+                ))
+            })?;
+        let mut metadata = StoreMetadata::from_plist(&plist)?;
+        if metadata.store_uuid.is_none() {
+            metadata.store_uuid = uuid;
+        }
+        Ok(metadata)
+    }
+
+    /// Set how to handle malformed rows for the next loads.
+    pub fn set_load_mode(&mut self, mode: LoadMode) {
+        self.report = LoadReport::new(mode);
+    }
+
+    /// The warnings about the rows skipped while loading.
+    pub fn warnings(&self) -> &[LoadWarning] {
+        self.report.warnings()
+    }
+
+    /// Load all the content of the catalog. The cached content is
+    /// moved into the `CatalogData`.
+    pub fn load_all(&mut self) -> Result<CatalogData> {
+        let keyword_tree = self.load_keywords_tree()?;
+        self.load_folders()?;
+        self.load_images()?;
+        self.load_stacks()?;
+        self.load_collections()?;
+        self.load_variants()?;
+        self.load_variant_metadata()?;
+        self.load_keyword_assignments()?;
+
+        Ok(CatalogData::new(Content {
+            root_collection_id: self.root_collection_id,
+            keywords: std::mem::take(&mut self.keywords),
+            keyword_tree,
+            folders: std::mem::take(&mut self.folders),
+            images: std::mem::take(&mut self.images),
+            stacks: std::mem::take(&mut self.stacks),
+            collections: std::mem::take(&mut self.collections),
+            variants: std::mem::take(&mut self.variants),
+            variant_metadata: std::mem::take(&mut self.variant_metadata),
+            keyword_assignments: std::mem::take(&mut self.keyword_assignments),
+        }))
+    }
+
+    pub fn load_keywords_tree(&mut self) -> Result<KeywordTree> {
+        let keywords = self.load_keywords()?;
 
         let mut tree = KeywordTree::new();
         let keyword = Keyword::default();
         tree.add_child(&keyword);
         tree.add_children(keywords);
 
-        tree
+        Ok(tree)
+    }
+
+    pub fn load_collection_tree(&mut self) -> Result<CollectionTree> {
+        let root = self.root_collection_id;
+        let collections = self.load_collections()?;
+
+        Ok(CollectionTree::new(root, collections))
     }
 
-    pub fn load_keywords(&mut self) -> &BTreeMap<CoId, Keyword> {
+    pub fn load_keywords(&mut self) -> Result<&BTreeMap<CoId, Keyword>> {
         if self.keywords.is_empty() {
-            if let Some(ref conn) = self.dbconn {
-                if let Some(entity) = self.entities_name_to_id.get("Keyword") {
-                    if let Ok(mut stmt) =
-                        conn.prepare("SELECT Z_PK, ZNAME, ZPARENT FROM ZKEYWORD WHERE Z_ENT=?1")
-                    {
-                        let mut rows = stmt.query([entity]).unwrap();
-                        while let Ok(Some(row)) = rows.next() {
-                            let name: String = row.get(1).unwrap();
-                            let keyword =
-                                Keyword::new(row.get(0).unwrap(), &name, row.get(2).unwrap_or(0));
-                            self.keywords.insert(keyword.id(), keyword);
-                        }
-                    }
-                }
-            }
+            let entity = self.entity("Keyword")?;
+            let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+            self.keywords = Keyword::load_objects(conn, &self.schema, entity, &mut self.report)?;
         }
-        &self.keywords
+        Ok(&self.keywords)
     }
 
-    pub fn load_folders(&mut self) -> &Folders {
+    pub fn load_folders(&mut self) -> Result<&Folders> {
         if self.folders.is_empty() {
-            if let Some(ref conn) = self.dbconn {
-                if let Some(entity) = self.entities_name_to_id.get("PathLocation") {
-                    self.folders = Folder::load_objects(conn, *entity);
-                }
-            }
+            let entity = self.entity("PathLocation")?;
+            let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+            self.folders = Folder::load_objects(conn, &self.schema, entity, &mut self.report)?;
         }
-        &self.folders
+        Ok(&self.folders)
     }
 
-    pub fn load_collections(&mut self) -> &Vec<Collection> {
+    pub fn load_collections(&mut self) -> Result<&Vec<Collection>> {
         if self.collections.is_empty() {
-            if let Some(ref conn) = self.dbconn {
-                self.collections = Collection::load_objects(conn, &self.entities_id_to_name);
-                self.collections
-                    .iter_mut()
-                    .for_each(|collection: &mut Collection| {
-                        collection.get_content(conn);
-                    });
-            }
+            self.check_loadable()?;
+            let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+            self.collections = Collection::load_objects(
+                conn,
+                &self.schema,
+                &self.entities_id_to_name,
+                &mut self.report,
+            )?;
+            Collection::load_content(conn, &self.schema, &mut self.collections, &mut self.report)?;
         }
-        &self.collections
+        Ok(&self.collections)
     }
 
-    pub fn load_images(&mut self) -> &Vec<Image> {
+    pub fn load_images(&mut self) -> Result<&Vec<Image>> {
         if self.images.is_empty() {
-            if let Some(ref conn) = self.dbconn {
-                if let Some(entity) = self.entities_name_to_id.get("Image") {
-                    self.images = Image::load_objects(conn, *entity);
-                }
-            }
+            let entity = self.entity("Image")?;
+            let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+            self.images = Image::load_objects(conn, &self.schema, entity, &mut self.report)?;
         }
-        &self.images
+        Ok(&self.images)
     }
 
-    pub fn load_stacks(&mut self) -> &Vec<Stack> {
+    pub fn load_stacks(&mut self) -> Result<&Vec<Stack>> {
         if self.stacks.is_empty() {
-            if let Some(ref conn) = self.dbconn {
-                if let Some(entity) = self.entities_name_to_id.get("Stack") {
-                    self.stacks = Stack::load_objects(conn, *entity);
-                    self.stacks.iter_mut().for_each(|stack: &mut Stack| {
-                        stack.get_content(conn);
-                    });
+            let entity = self.entity("Stack")?;
+            let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+            self.stacks = Stack::load_objects(conn, &self.schema, entity, &mut self.report)?;
+            Stack::load_content(conn, &self.schema, &mut self.stacks, &mut self.report)?;
+        }
+        Ok(&self.stacks)
+    }
+
+    pub fn load_variants(&mut self) -> Result<&Vec<Variant>> {
+        if self.variants.is_empty() {
+            let entity = self.entity("Variant")?;
+            let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+            self.variants = Variant::load_objects(conn, &self.schema, entity, &mut self.report)?;
+        }
+        Ok(&self.variants)
+    }
+
+    pub fn load_variant_metadata(&mut self) -> Result<&Vec<VariantMetadata>> {
+        if self.variant_metadata.is_empty() {
+            self.check_loadable()?;
+            let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+            self.variant_metadata =
+                VariantMetadata::load_objects(conn, &self.schema, &mut self.report)?;
+        }
+        Ok(&self.variant_metadata)
+    }
+
+    /// Load the keywords assigned to the images and variants, from
+    /// the join tables discovered in the database.
+    pub fn load_keyword_assignments(&mut self) -> Result<&KeywordAssignments> {
+        if self.keyword_assignments.is_empty() {
+            let entity = self.entity("Keyword")?;
+            let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+            self.keyword_assignments = KeywordAssignments::load(
+                conn,
+                &self.entities_id_to_name,
+                entity,
+                &mut self.report,
+            )?;
+        }
+        Ok(&self.keyword_assignments)
+    }
+
+    /// Iterate over the folders without loading them all.
+    pub fn folders_iter(&self) -> Result<ObjectIter<'_, Folder>> {
+        self.objects_iter("PathLocation")
+    }
+
+    /// Iterate over the images without loading them all.
+    pub fn images_iter(&self) -> Result<ObjectIter<'_, Image>> {
+        self.objects_iter("Image")
+    }
+
+    /// Iterate over the stacks without loading them all. The content
+    /// of the stacks isn't loaded.
+    pub fn stacks_iter(&self) -> Result<ObjectIter<'_, Stack>> {
+        self.objects_iter("Stack")
+    }
+
+    /// Iterate over the variants without loading them all.
+    pub fn variants_iter(&self) -> Result<ObjectIter<'_, Variant>> {
+        self.objects_iter("Variant")
+    }
+
+    fn objects_iter<T: FromRow>(&self, entity_name: &str) -> Result<ObjectIter<'_, T>> {
+        let entity = self.entity(entity_name)?;
+        let conn = self.dbconn.as_ref().ok_or(Error::NoDatabase)?;
+        ObjectIter::new(conn, &self.schema, entity, self.report.mode())
+    }
+}
+
+/// `path` with `suffix` appended to the file name.
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_os_string();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+/// Copy the database at `db_path` and its journal files into `dir`.
+/// The `-shm` isn't copied, SQLite rebuilds it from the WAL. Retry
+/// until the files don't change during the copy, at most for
+/// `timeout`. Return the path of the copy.
+fn copy_database(db_path: &Path, dir: &Path, timeout: Duration) -> Result<PathBuf> {
+    let copy = dir.join(DB_FILENAME);
+    let mut files = vec![(PathBuf::from(db_path), copy.clone())];
+    files.extend(
+        JOURNAL_SUFFIXES
+            .iter()
+            .map(|suffix| (with_suffix(db_path, suffix), with_suffix(&copy, suffix))),
+    );
+    let state = |files: &[(PathBuf, PathBuf)]| {
+        files
+            .iter()
+            .map(|(path, _)| {
+                std::fs::metadata(path)
+                    .ok()
+                    .map(|metadata| (metadata.len(), metadata.modified().ok()))
+            })
+            .collect::<Vec<_>>()
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        let before = state(&files);
+        // The journals first, as a checkpoint write the database
+        // from the WAL.
+        for (path, copy) in files.iter().rev() {
+            match std::fs::copy(path, copy) {
+                Ok(_) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound && path != db_path => {
+                    let _ = std::fs::remove_file(copy);
                 }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if state(&files) == before {
+            return Ok(copy);
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::CatalogInUse);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Escape `path` for use in a SQLite URI.
+fn uri_path(path: &Path) -> String {
+    let mut uri = String::new();
+    for c in path.to_string_lossy().chars() {
+        match c {
+            '%' | '?' | '#' => uri.push_str(&format!("%{:02X}", c as u32)),
+            _ => uri.push(c),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use std::path::Path;
+
+    use super::{Catalog, CatalogVersion};
+    use crate::fixtures::Fixture;
+    use crate::{CollectionType, Error, LoadMode};
+
+    /// Load the catalog from `fixture`. Keep the `TempDir` alive
+    /// while using the catalog.
+    fn load_fixture(fixture: &Fixture) -> (tempfile::TempDir, Catalog) {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = fixture.create_bundle(dir.path());
+
+        let mut catalog = Catalog::new(&bundle);
+        catalog.open().unwrap();
+        (dir, catalog)
+    }
+
+    /// Load the catalog from the fixture, after running `sql` on it.
+    fn load_fixture_with(sql: &str) -> (tempfile::TempDir, Catalog) {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = Fixture::new(2300).create_bundle(dir.path());
+        let conn = rusqlite::Connection::open(bundle.join(super::DB_FILENAME)).unwrap();
+        conn.execute_batch(sql).unwrap();
+        drop(conn);
+
+        let mut catalog = Catalog::new(&bundle);
+        catalog.open().unwrap();
+        (dir, catalog)
+    }
+
+    /// Check the catalog of `version`, with the 11 and 12 schema of
+    /// the fixture.
+    fn check_catalog(version: i32, catalog_version: CatalogVersion) {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(version));
+        assert_eq!(catalog.version, version);
+        assert_eq!(catalog.catalog_version, catalog_version);
+        assert!(catalog.catalog_version.is_supported());
+        assert_eq!(catalog.schema_version, catalog_version);
+        assert_eq!(catalog.root_collection_id, 1);
+
+        let keywords = catalog.load_keywords().unwrap();
+        assert_eq!(keywords.len(), 3);
+        assert_eq!(keywords[&2].name, "Montréal");
+        assert_eq!(keywords[&2].parent, 1);
+
+        let folders = catalog.load_folders().unwrap();
+        assert_eq!(folders.len(), 2);
+        assert!(folders[1].is_relative);
+        assert_eq!(folders[0].root_folder, "/Volumes/Photos");
+
+        let images = catalog.load_images().unwrap();
+        assert_eq!(images.len(), 3);
+        assert_eq!(images[0].file_name, "DSCF0001.RAF");
+        assert_eq!(images[0].gps_lat, Some(45.5));
+        assert_eq!(images[1].gps_lat, None);
+
+        let stacks = catalog.load_stacks().unwrap();
+        assert_eq!(stacks.len(), 3);
+        assert_eq!(stacks[0].content, Some(vec![1, 2]));
+
+        let collections = catalog.load_collections().unwrap();
+        assert_eq!(collections.len(), 8);
+        assert_eq!(collections[1].content, Some(vec![1, 2]));
+        assert_eq!(collections[5].content, Some(vec![3]));
+    }
+
+    #[test]
+    fn test_co11() {
+        check_catalog(1106, CatalogVersion::Co1106);
+    }
+
+    #[test]
+    fn test_co12() {
+        check_catalog(1200, CatalogVersion::Co1200);
+        check_catalog(1210, CatalogVersion::Co1210);
+    }
+
+    /// There is no fixture with the schema of the versions after 12,
+    /// only their detection is checked.
+    #[test]
+    fn test_version() {
+        for version in [1300, 2000, 2100, 2200, 2300] {
+            let catalog_version = CatalogVersion::from(version);
+            assert!(catalog_version.is_supported());
+            assert_eq!(catalog_version.number(), version);
+            assert_eq!(CatalogVersion::closest(version), catalog_version);
+        }
+        assert_eq!(CatalogVersion::from(1250), CatalogVersion::Unknown);
+        assert_eq!(CatalogVersion::closest(1250), CatalogVersion::Co1210);
+        assert_eq!(CatalogVersion::closest(1000), CatalogVersion::Unknown);
+    }
+
+    #[test]
+    fn test_unknown_version() {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(900));
+        assert_eq!(catalog.catalog_version, CatalogVersion::Unknown);
+        assert!(!catalog.catalog_version.is_supported());
+        assert_eq!(catalog.schema_version, CatalogVersion::Unknown);
+        assert!(matches!(
+            catalog.load_images(),
+            Err(Error::UnsupportedVersion)
+        ));
+
+        // Newer than we know.
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2450));
+        assert_eq!(catalog.catalog_version, CatalogVersion::Unknown);
+        assert_eq!(catalog.schema_version, CatalogVersion::Co2300);
+        assert_eq!(catalog.load_images().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_info() {
+        let (_dir, catalog) = load_fixture(&Fixture::new(2200));
+        let info = catalog.load_info().unwrap();
+        assert_eq!(info.root_collection_id, 1);
+        assert_eq!(info.history.len(), 2);
+        assert_eq!(info.history[0].version, 1106);
+        assert_eq!(info.history[0].compatibility, None);
+        assert_eq!(info.current().map(|info| info.version), Some(2200));
+        assert_eq!(info.app_version(), Some("22.0.0.1"));
+        assert_eq!(info.compatible_version(), Some(1106));
+        assert_eq!(info.format(), Some("22.0.0.1"));
+    }
+
+    #[test]
+    fn test_metadata() {
+        let (_dir, catalog) = load_fixture(&Fixture::new(2100));
+        let metadata = catalog.load_metadata().unwrap();
+        assert_eq!(
+            metadata.store_uuid.as_deref(),
+            Some("8C0A3B5E-2C1F-4E4B-9D3A-6F2E1A0B9C8D")
+        );
+        assert_eq!(metadata.closest_version(), CatalogVersion::Co2100);
+        assert!(metadata.entities().any(|entity| entity == "Image"));
+
+        // No ZVERSIONINFO row. Use the metadata.
+        let mut fixture = Fixture::new(2100);
+        fixture.version_info = false;
+        let (_dir, mut catalog) = load_fixture(&fixture);
+        assert_eq!(catalog.version, 0);
+        assert_eq!(catalog.catalog_version, CatalogVersion::Unknown);
+        assert_eq!(catalog.schema_version, CatalogVersion::Co2100);
+        assert_eq!(catalog.load_keywords().unwrap().len(), 3);
+
+        // The IPTC and user metadata of the variants.
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let metadata = catalog.load_variant_metadata().unwrap();
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(metadata[0].variant, 1);
+        assert_eq!(metadata[0].title.as_deref(), Some("Old port"));
+        assert_eq!(metadata[0].city.as_deref(), Some("Montréal"));
+        assert_eq!(metadata[0].headline, None);
+        assert_eq!(
+            metadata[0].other.get("ZCUSTOMFIELD"),
+            Some(&rusqlite::types::Value::Text("Client A".into()))
+        );
+        assert_eq!(
+            metadata[0].other.get("ZEXTRASCORE"),
+            Some(&rusqlite::types::Value::Integer(7))
+        );
+        // NULL aren't kept.
+        assert!(metadata[1].other.is_empty());
+
+        let data = catalog.load_all().unwrap();
+        let metadata = data.variant(1).unwrap().metadata().unwrap();
+        assert_eq!(metadata.copyright.as_deref(), Some("© 2019"));
+        assert!(data.variant(3).unwrap().metadata().is_none());
+    }
+
+    #[test]
+    fn test_no_gps_columns() {
+        let mut fixture = Fixture::new(2300);
+        fixture.gps = false;
+        let (_dir, mut catalog) = load_fixture(&fixture);
+
+        let images = catalog.load_images().unwrap();
+        assert_eq!(images.len(), 3);
+        assert!(images.iter().all(|image| image.gps_lat.is_none()));
+
+        let (_dir, mut catalog) =
+            load_fixture_with("UPDATE ZIMAGE SET ZGPSLATITUDE = 'north' WHERE Z_PK = 1;");
+        let images = catalog.load_images().unwrap();
+        assert_eq!(images.len(), 3);
+        assert!(images[0].gps_lat.is_none());
+        assert!(catalog.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_malformed_capture_info() {
+        let (_dir, mut catalog) = load_fixture_with(
+            "UPDATE ZIMAGE SET ZEXP_ISO = 'auto', ZEXP_CAPTUREDATE = 'today' WHERE Z_PK = 1;",
+        );
+        let images = catalog.load_images().unwrap();
+        assert_eq!(images.len(), 3);
+        let capture = &images[0].capture;
+        assert!(capture.iso.is_none());
+        assert!(capture.date.is_none());
+        assert_eq!(capture.camera_model.as_deref(), Some("X-T3"));
+        assert!(catalog.warnings().is_empty());
+    }
+
+    fn file_state(path: &Path) -> (std::time::SystemTime, u64) {
+        let mtime = std::fs::metadata(path).unwrap().modified().unwrap();
+        let mut hasher = DefaultHasher::new();
+        std::fs::read(path).unwrap().hash(&mut hasher);
+        (mtime, hasher.finish())
+    }
+
+    #[test]
+    fn test_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = Fixture::new(2300).create_bundle(dir.path());
+        let mut catalog = Catalog::new(&bundle);
+        let db_path = catalog.db_path();
+        assert!(db_path.ends_with("Capture One Catalog.cocatalogdb"));
+        let before = file_state(&db_path);
+
+        catalog.open().unwrap();
+        catalog.load_info().unwrap();
+        catalog.load_metadata().unwrap();
+        catalog.load_keywords_tree().unwrap();
+        catalog.load_folders().unwrap();
+        catalog.load_images().unwrap();
+        catalog.load_stacks().unwrap();
+        catalog.load_collections().unwrap();
+        catalog.load_variants().unwrap();
+        catalog.load_variant_metadata().unwrap();
+        drop(catalog);
+
+        assert_eq!(file_state(&db_path), before);
+        let files = std::fs::read_dir(&bundle).unwrap().count();
+        assert_eq!(files, 1);
+    }
+
+    #[test]
+    fn test_in_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = Fixture::new(2300).create_bundle(dir.path());
+        let mut catalog = Catalog::new(&bundle);
+        assert!(!catalog.is_in_use());
+
+        // Capture One has the catalog open.
+        let conn = rusqlite::Connection::open(catalog.db_path()).unwrap();
+        conn.execute("UPDATE ZKEYWORD SET ZNAME='lieux' WHERE Z_PK=1", [])
+            .unwrap();
+        assert!(catalog.is_in_use());
+        assert!(matches!(catalog.open(), Err(Error::CatalogInUse)));
+        drop(conn);
+
+        assert!(!catalog.is_in_use());
+        catalog.open().unwrap();
+
+        // Left over from a clean close.
+        std::fs::write(bundle.join("Capture One Catalog.cocatalogdb-wal"), b"").unwrap();
+        std::fs::write(bundle.join("Capture One Catalog.cocatalogdb-shm"), b"shm").unwrap();
+        assert!(!catalog.is_in_use());
+        catalog.open().unwrap();
+    }
+
+    /// The file names in `dir`.
+    fn listing(dir: &Path) -> Vec<String> {
+        let mut names = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = Fixture::new(2300).create_bundle(dir.path());
+        let mut catalog = Catalog::new(&bundle);
+
+        // Idle, the catalog is opened immutable.
+        let before = listing(&bundle);
+        catalog.open_snapshot().unwrap();
+        assert_eq!(catalog.load_keywords().unwrap().len(), 3);
+        assert_eq!(listing(&bundle), before);
+
+        // Capture One has the catalog open, with changes in the WAL.
+        let conn = rusqlite::Connection::open(catalog.db_path()).unwrap();
+        conn.pragma_update(None, "wal_autocheckpoint", 0).unwrap();
+        conn.execute("INSERT INTO ZKEYWORD VALUES (4, 3, 'Québec', 1)", [])
+            .unwrap();
+
+        let mut catalog = Catalog::new(&bundle);
+        let before = listing(&bundle);
+        catalog.open_snapshot().unwrap();
+        assert_eq!(listing(&bundle), before);
+        // Modified after the snapshot.
+        conn.execute("INSERT INTO ZKEYWORD VALUES (5, 3, 'Toronto', 1)", [])
+            .unwrap();
+
+        let keywords = catalog.load_keywords().unwrap();
+        assert_eq!(keywords.len(), 4);
+        assert_eq!(keywords[&4].name, "Québec");
+
+        // Capture One holds an exclusive lock, the copy doesn't lock.
+        conn.pragma_update(None, "locking_mode", "EXCLUSIVE")
+            .unwrap();
+        conn.execute("INSERT INTO ZKEYWORD VALUES (6, 3, 'Ottawa', 1)", [])
+            .unwrap();
+        let mut catalog = Catalog::new(&bundle);
+        let before = listing(&bundle);
+        catalog.open_snapshot().unwrap();
+        assert_eq!(listing(&bundle), before);
+        assert_eq!(catalog.load_keywords().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn test_archive() {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = Fixture::new(2200).create_bundle(dir.path());
+        let db = std::fs::read(bundle.join(super::DB_FILENAME)).unwrap();
+
+        let archive_path = dir.path().join("Test 2023-05-01 10.00.zip");
+        let mut archive = zip::ZipWriter::new(std::fs::File::create(&archive_path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        archive
+            .start_file("Test.cocatalog/Cache/Thumbnails/readme.txt", options)
+            .unwrap();
+        archive.write_all(b"Not a database").unwrap();
+        archive
+            .start_file("Test.cocatalog/Capture One Catalog.cocatalogdb", options)
+            .unwrap();
+        archive.write_all(&db).unwrap();
+        archive.finish().unwrap();
+
+        let mut catalog = Catalog::new(&archive_path);
+        assert!(catalog.is_archive());
+        catalog.open().unwrap();
+        assert_eq!(catalog.catalog_version, CatalogVersion::Co2200);
+        assert_eq!(catalog.load_keywords().unwrap().len(), 3);
+        assert_eq!(catalog.load_images().unwrap().len(), 3);
+        assert_eq!(catalog.load_collections().unwrap().len(), 8);
+
+        // Not a catalog backup.
+        let archive_path = dir.path().join("Other.zip");
+        let mut archive = zip::ZipWriter::new(std::fs::File::create(&archive_path).unwrap());
+        archive.start_file("readme.txt", options).unwrap();
+        archive.finish().unwrap();
+        let mut catalog = Catalog::new(&archive_path);
+        assert!(matches!(catalog.open(), Err(Error::NoDatabase)));
+    }
+
+    #[test]
+    fn test_malformed_rows() {
+        let (_dir, mut catalog) = load_fixture_with(
+            "INSERT INTO ZIMAGE (Z_PK, Z_ENT, ZIMAGEUUID, ZIMAGELOCATION, ZDISPLAYNAME, \
+             ZIMAGEFILENAME, ZIMAGECLASSIFICATION, ZEXP_FORMAT) \
+             VALUES (4, 5, 'uuid', 1, 'DSCF0004', NULL, 19, 'JPEG');
+             INSERT INTO ZKEYWORD VALUES (4, 3, NULL, 1);
+             INSERT INTO ZSTACKIMAGELINK VALUES (5, NULL, 2, NULL, 1), (6, NULL, 0, 3, NULL),
+                 (7, NULL, -1, 3, 1);
+             INSERT INTO Z_14KEYWORDS VALUES (2, NULL), ('two', 3);",
+        );
+        assert_eq!(catalog.load_images().unwrap().len(), 3);
+        assert_eq!(catalog.load_keywords().unwrap().len(), 3);
+        let warnings = catalog.warnings();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0].table, "ZIMAGE");
+        assert_eq!(warnings[0].id, 4);
+        assert_eq!(warnings[0].column.as_deref(), Some("ZIMAGEFILENAME"));
+        assert_eq!(warnings[1].table, "ZKEYWORD");
+        assert_eq!(warnings[1].column.as_deref(), Some("ZNAME"));
+
+        // The link without a stack is skipped, the one without an
+        // image is reported.
+        let stacks = catalog.load_stacks().unwrap();
+        assert_eq!(stacks[0].content, Some(vec![3, 1, 2]));
+        assert_eq!(stacks[1].content, Some(vec![3]));
+        let warnings = catalog.warnings();
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(warnings[2].table, "ZSTACKIMAGELINK");
+        assert_eq!(warnings[2].id, 5);
+        assert_eq!(warnings[2].column.as_deref(), Some("ZIMAGE"));
+
+        let assignments = catalog.load_keyword_assignments().unwrap();
+        assert_eq!(assignments.variants.len(), 1);
+        let warnings = catalog.warnings();
+        assert_eq!(warnings.len(), 5);
+        assert_eq!(warnings[3].table, "Z_14KEYWORDS");
+        assert_eq!(warnings[3].id, 2);
+        assert_eq!(warnings[3].column.as_deref(), Some("Z_3KEYWORDS"));
+        // The owner itself is malformed.
+        assert_eq!(warnings[4].id, 0);
+        assert_eq!(warnings[4].column.as_deref(), Some("Z_14VARIANTS"));
+
+        let mut catalog = Catalog::new(&catalog.path);
+        catalog.set_load_mode(LoadMode::Strict);
+        catalog.open().unwrap();
+        match catalog.load_images() {
+            Err(Error::MalformedRow(warning)) => {
+                assert_eq!(warning.id, 4);
+                assert_eq!(warning.column.as_deref(), Some("ZIMAGEFILENAME"));
             }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_iterators() {
+        let (_dir, mut catalog) = load_fixture_with(
+            "INSERT INTO ZIMAGE (Z_PK, Z_ENT, ZIMAGEUUID, ZIMAGELOCATION, ZDISPLAYNAME, \
+             ZIMAGEFILENAME, ZIMAGECLASSIFICATION, ZEXP_FORMAT) \
+             VALUES (4, 5, 'uuid', 1, 'DSCF0004', NULL, 19, 'JPEG');",
+        );
+        let mut images = catalog.images_iter().unwrap().with_page_size(2);
+        let ids = (&mut images)
+            .map(|image| image.map(|image| image.id))
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(images.warnings().len(), 1);
+        assert_eq!(images.warnings()[0].id, 4);
+
+        catalog.set_load_mode(LoadMode::Strict);
+        let ids = catalog
+            .images_iter()
+            .unwrap()
+            .with_page_size(2)
+            .map(|image| image.map(|image| image.id))
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 4);
+        assert!(ids[..3].iter().all(|id| id.is_ok()));
+        assert!(matches!(ids[3], Err(Error::MalformedRow(ref warning)) if warning.id == 4));
+
+        let loaded = catalog.load_folders().unwrap().clone();
+        let folders = catalog
+            .folders_iter()
+            .unwrap()
+            .with_page_size(1)
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(folders.len(), loaded.len());
+        assert_eq!(folders[0].path_from_root, loaded[0].path_from_root);
+
+        let stacks = catalog
+            .stacks_iter()
+            .unwrap()
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(stacks.len(), 3);
+        assert!(stacks.iter().all(|stack| stack.content.is_none()));
+    }
+
+    #[test]
+    fn test_collection_types() {
+        let (_dir, mut catalog) = load_fixture_with(
+            "INSERT INTO ZENTITIES VALUES (16, 'SmartAlbumCollection'), (17, 'GroupCollection'),
+                 (18, 'FutureCollection');
+             INSERT INTO ZCOLLECTION (Z_PK, Z_ENT, ZNAME, ZPARENT) VALUES
+                 (9, 17, 'Clients', 1), (10, 16, '5 stars', 9), (11, 18, NULL, 1),
+                 (12, 99, NULL, 1);",
+        );
+        let collections = catalog.load_collections().unwrap();
+        assert_eq!(collections.len(), 12);
+        assert!(
+            matches!(collections[8].collection_type, CollectionType::Group(ref name) if name == "Clients")
+        );
+        assert!(
+            matches!(collections[9].collection_type, CollectionType::SmartAlbum(ref name) if name == "5 stars")
+        );
+        assert!(
+            matches!(collections[10].collection_type, CollectionType::Other(ref name) if name == "FutureCollection")
+        );
+        // Not in ZENTITIES.
+        assert!(
+            matches!(collections[11].collection_type, CollectionType::Other(ref name) if name == "entity 99")
+        );
+        assert!(catalog.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_schema_mismatch() {
+        let (_dir, mut catalog) = load_fixture_with(
+            "DROP TABLE ZSTACKIMAGELINK;
+             ALTER TABLE ZPATHLOCATION DROP COLUMN ZRELATIVEPATH;
+             DELETE FROM ZENTITIES WHERE ZNAME='Keyword';
+             DELETE FROM ZIMAGE;",
+        );
+        // Not open.
+        let mut closed = Catalog::new(&catalog.path);
+        assert!(matches!(closed.load_images(), Err(Error::NoDatabase)));
+        // Empty is not an error.
+        assert!(catalog.load_images().unwrap().is_empty());
+        assert!(matches!(
+            catalog.load_keywords(),
+            Err(Error::MissingEntity(entity)) if entity == "Keyword"
+        ));
+        assert!(matches!(
+            catalog.load_folders(),
+            Err(Error::MissingColumn(table, column))
+                if table == "ZPATHLOCATION" && column == "ZRELATIVEPATH"
+        ));
+        assert!(matches!(
+            catalog.load_stacks(),
+            Err(Error::MissingTable(table)) if table == "ZSTACKIMAGELINK"
+        ));
+    }
+
+    #[test]
+    fn test_variants() {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let variants = catalog.load_variants().unwrap();
+        assert_eq!(variants.len(), 4);
+        assert_eq!(variants[0].image, 1);
+        assert_eq!(variants[0].processing_engine, Some(1200));
+        assert_eq!(variants[0].product_id.as_deref(), Some("FujiXT3"));
+        assert_eq!(variants[0].default_layer, Some(1));
+        assert_eq!(variants[2].product_id, None);
+
+        let data = catalog.load_all().unwrap();
+        let image = data.image(1).unwrap();
+        let variants = image.variants().iter().map(|v| v.id).collect::<Vec<_>>();
+        assert_eq!(variants, vec![1, 4]);
+        assert_eq!(data.variant(2).unwrap().image_of().unwrap().id, 2);
+        assert_eq!(data.primary_variant(1).unwrap().id, 1);
+    }
+
+    #[test]
+    fn test_variant_rating() {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let variants = catalog.load_variants().unwrap();
+        assert_eq!(variants[0].rating, Some(4));
+        assert_eq!(variants[0].color_tag, Some(crate::ColorTag::Green));
+        assert_eq!(variants[0].pick, Some(crate::PickState::Picked));
+        assert_eq!(variants[1].xmp_rating(), Some(-1));
+        assert_eq!(variants[1].color_tag, None);
+        assert_eq!(variants[2].rating, None);
+        assert_eq!(variants[2].xmp_rating(), None);
+
+        let data = catalog.load_all().unwrap();
+        assert_eq!(data.primary_variant(1).unwrap().rating, Some(4));
+        assert_eq!(data.primary_variant(3).unwrap().rating, None);
+    }
+
+    #[test]
+    fn test_keyword_assignments() {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let assignments = catalog.load_keyword_assignments().unwrap();
+        assert_eq!(assignments.variants[&1], vec![2]);
+        assert_eq!(assignments.images[&2], vec![3]);
+
+        let data = catalog.load_all().unwrap();
+        assert_eq!(data.keywords_for_variant(1, false), vec![2]);
+        assert_eq!(data.keywords_for_variant(1, true), vec![1, 2]);
+        assert_eq!(data.keywords_for_image(1, true), vec![1, 2]);
+        assert_eq!(data.keywords_for_image(2, false), vec![3]);
+        assert!(data.keywords_for_image(3, true).is_empty());
+        assert_eq!(data.images_with(2, false), vec![1]);
+        assert!(data.images_with(1, false).is_empty());
+        assert_eq!(data.images_with(1, true), vec![1]);
+        assert_eq!(data.images_with(3, true), vec![2]);
+    }
+
+    #[test]
+    fn test_capture_info() {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let data = catalog.load_all().unwrap();
+        assert!(data.image(3).unwrap().capture.date.is_none());
+        assert_eq!(
+            data.image(1)
+                .unwrap()
+                .capture
+                .date
+                .and_then(|d| d.to_rfc3339()),
+            Some("2020-01-06T10:40:00Z".to_string())
+        );
+        let capture = &data.image(1).unwrap().capture;
+        assert_eq!(capture.time_zone.as_deref(), Some("-05:00"));
+        assert_eq!(capture.camera_make.as_deref(), Some("FUJIFILM"));
+        assert_eq!(capture.camera_model.as_deref(), Some("X-T3"));
+        assert_eq!(capture.lens.as_deref(), Some("XF23mmF2 R WR"));
+        assert_eq!(capture.focal_length, Some(23.0));
+        assert_eq!(capture.aperture, Some(5.6));
+        assert_eq!(capture.shutter_speed_display().as_deref(), Some("1/250"));
+        assert_eq!(capture.iso, Some(160));
+        assert_eq!(capture.exposure_compensation, Some(-0.67));
+        assert_eq!(capture.oriented_dimensions(), Some((4160, 6240)));
+        assert_eq!(capture.file_size, Some(51234567));
+        assert!(data.image(2).unwrap().capture.camera_make.is_none());
+    }
+
+    #[test]
+    fn test_collection_dates() {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let data = catalog.load_all().unwrap();
+        let album = data.collection(6).unwrap();
+        assert_eq!(album.parent, 5);
+        assert_eq!(album.date_created.map(|d| d.timestamp()), Some(600000000.0));
+        assert_eq!(
+            album.date_modified.map(|d| d.unix_timestamp()),
+            Some(1578393600.5)
+        );
+        assert_eq!(album.sort_order.as_deref(), Some("captureDate"));
+        assert_eq!(album.primary_variant, Some(2));
+        assert!(data.collection(5).unwrap().date_created.is_none());
+    }
+
+    #[test]
+    fn test_navigation() {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let data = catalog.load_all().unwrap();
+        let image = data.image(1).unwrap();
+        assert_eq!(image.folder_of().unwrap().path_from_root, "2019/");
+        let stacks = image.stacks().iter().map(|s| s.id).collect::<Vec<_>>();
+        assert_eq!(stacks, vec![1, 3]);
+        let stack = data.stack(1).unwrap();
+        let images = stack.images().iter().map(|i| i.id).collect::<Vec<_>>();
+        assert_eq!(images, vec![1, 2]);
+        assert_eq!(stack.pick_of().unwrap().id, 1);
+        assert_eq!(stack.collection_of().unwrap().id, 2);
+        assert_eq!(data.folder(2).unwrap().images().len(), 1);
+        let projects = data.collection(5).unwrap();
+        let children = projects.children().iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(children, vec![6]);
+        let stacks = data.collection(6).unwrap().stacks();
+        assert_eq!(stacks.iter().map(|s| s.id).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(projects.parent_of().unwrap().id, 1);
+    }
+
+    #[test]
+    fn test_collection_tree() {
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let data = catalog.load_all().unwrap();
+        let tree = data.collection_tree();
+        assert_eq!(tree.trash(), Some(3));
+        assert_eq!(tree.children_for(1), vec![2, 4, 5, 8, 7, 3]);
+        assert_eq!(tree.path(6), "Projects/Best of");
+    }
+
+    #[test]
+    fn test_load_all() {
+        fn is_send_sync<T: Send + Sync>(_: &T) {}
+
+        let (_dir, mut catalog) = load_fixture(&Fixture::new(2300));
+        let data = catalog.load_all().unwrap();
+        is_send_sync(&data);
+
+        assert_eq!(data.root_collection_id, 1);
+        assert_eq!(data.keywords().len(), 3);
+        assert_eq!(data.keyword_tree().children_for(1), vec![2]);
+        assert_eq!(data.folders().len(), 2);
+        assert_eq!(data.images().len(), 3);
+        assert_eq!(data.stacks().len(), 3);
+        assert_eq!(data.collections().len(), 8);
+
+        assert_eq!(data.keyword(2).map(|k| k.name.as_str()), Some("Montréal"));
+        assert!(data.folder(2).unwrap().is_relative);
+        assert_eq!(data.image(3).unwrap().file_name, "DSCF0002.MOV");
+        assert_eq!(data.stack(3).unwrap().collection, 6);
+        assert!(data.image(42).is_none());
+
+        let data = std::sync::Arc::new(data);
+        let handles = (0..2)
+            .map(|_| {
+                let data = data.clone();
+                std::thread::spawn(move || data.images().len())
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 3);
         }
-        &self.stacks
     }
 }
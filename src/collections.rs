@@ -9,7 +9,8 @@ use std::fmt;
 
 use rusqlite::params;
 
-use super::CoId;
+use super::schema::{Query, Schema};
+use super::{CoDate, CoId, LoadReport, Result};
 
 #[derive(Debug, Default)]
 pub enum CollectionType {
@@ -25,6 +26,12 @@ pub enum CollectionType {
     CatalogInternalImages,
     /// CatalogFolderCollection (ZFOLDERLOCATION on ZPATHLOCATION `Folder`)
     Folder(CoId),
+    /// SmartAlbumCollection entity (ZNAME)
+    SmartAlbum(String),
+    /// GroupCollection entity (ZNAME)
+    Group(String),
+    /// Any other entity (entity name)
+    Other(String),
 }
 
 impl fmt::Display for CollectionType {
@@ -37,7 +44,23 @@ impl fmt::Display for CollectionType {
             CollectionType::Trash => f.pad("Trash"),
             CollectionType::CatalogInternalImages => f.pad("All catalog images"),
             CollectionType::Folder(id) => f.pad(format!("Path folder: {}", id).as_str()),
-            _ => f.pad("Invalid"),
+            CollectionType::SmartAlbum(ref s) => f.pad(format!("Smart: \"{}\"", s).as_str()),
+            CollectionType::Group(ref s) => f.pad(format!("Group: \"{}\"", s).as_str()),
+            CollectionType::Other(ref s) => f.pad(format!("Other: {}", s).as_str()),
+            CollectionType::Invalid => f.pad("Invalid"),
+        }
+    }
+}
+
+impl CollectionType {
+    /// The name given by the user, if any.
+    pub fn name(&self) -> Option<&str> {
+        match *self {
+            CollectionType::Album(ref s)
+            | CollectionType::VirtualFolder(ref s)
+            | CollectionType::SmartAlbum(ref s)
+            | CollectionType::Group(ref s) => Some(s),
+            _ => None,
         }
     }
 }
@@ -46,62 +69,109 @@ pub struct Collection {
     pub id: CoId,
     pub collection_type: CollectionType,
     pub parent: CoId,
+    /// Order of the collection among its siblings (ZCOLLECTIONINDX).
+    pub index: i64,
+    /// The trash collection, for the project (ZTRASHCOLLECTION).
+    pub trash: Option<CoId>,
+    pub date_created: Option<CoDate>,
+    pub date_modified: Option<CoDate>,
+    /// The key to sort the content on (ZSORTORDER).
+    pub sort_order: Option<String>,
+    /// The cover variant (ZPRIMARYVARIANT).
+    pub primary_variant: Option<CoId>,
     /// Content: id of stack. None mean it hasn't been loaded.
     pub content: Option<Vec<CoId>>,
 }
 
 impl Collection {
-    pub fn load_objects(
+    /// Build the collection from `row`.
+    fn from_row(row: &rusqlite::Row, entity_name: &str) -> rusqlite::Result<Collection> {
+        let id: CoId = row.get(1)?;
+        let parent: CoId = row.get::<usize, Option<CoId>>(3)?.unwrap_or(0);
+        let collection_type = match entity_name {
+            "ProjectCollection" => CollectionType::Project,
+            "CatalogAllImagesCollection" => CollectionType::CatalogAll,
+            "CatalogInternalImagesCollection" => CollectionType::CatalogInternalImages,
+            "TrashCollection" => CollectionType::Trash,
+            "AlbumCollection" => CollectionType::Album(row.get(2)?),
+            "CatalogFolderCollection" => CollectionType::Folder(row.get(4)?),
+            "VirtualFolderCollection" => CollectionType::VirtualFolder(row.get(2)?),
+            "SmartAlbumCollection" => {
+                CollectionType::SmartAlbum(row.get::<usize, Option<String>>(2)?.unwrap_or_default())
+            }
+            "GroupCollection" => {
+                CollectionType::Group(row.get::<usize, Option<String>>(2)?.unwrap_or_default())
+            }
+            _ => {
+                log::info!("Unhandled collection entity {}", entity_name);
+                CollectionType::Other(entity_name.to_string())
+            }
+        };
+        Ok(Collection {
+            id,
+            collection_type,
+            parent,
+            index: row.get::<usize, Option<i64>>(5)?.unwrap_or(0),
+            trash: row.get(6)?,
+            date_created: row.get(7)?,
+            date_modified: row.get(8)?,
+            sort_order: row.get(9)?,
+            primary_variant: row.get(10)?,
+            content: None,
+        })
+    }
+
+    pub(crate) fn load_objects(
         conn: &rusqlite::Connection,
+        schema: &Schema,
         entities: &HashMap<CoId, String>,
-    ) -> Vec<Collection> {
+        report: &mut LoadReport,
+    ) -> Result<Vec<Collection>> {
         let mut collections: Vec<Collection> = vec![];
 
-        if let Ok(mut stmt) =
-            conn.prepare("SELECT Z_ENT, Z_PK, ZNAME, ZPARENT, ZFOLDERLOCATION FROM ZCOLLECTION")
-        {
-            let mut rows = stmt.query(params![]).unwrap();
-            while let Ok(Some(row)) = rows.next() {
-                let entity: CoId = row.get(0).unwrap();
-                if let Some(entity_name) = entities.get(&entity) {
-                    let id: CoId = row.get(1).unwrap();
-                    let parent: CoId = row.get(3).unwrap_or(0);
-                    let collection_type = match entity_name.as_str() {
-                        "ProjectCollection" => CollectionType::Project,
-                        "CatalogAllImagesCollection" => CollectionType::CatalogAll,
-                        "CatalogInternalImagesCollection" => CollectionType::CatalogInternalImages,
-                        "TrashCollection" => CollectionType::Trash,
-                        "AlbumCollection" => CollectionType::Album(row.get(2).unwrap()),
-                        "CatalogFolderCollection" => CollectionType::Folder(row.get(4).unwrap()),
-                        "VirtualFolderCollection" => {
-                            CollectionType::VirtualFolder(row.get(2).unwrap())
-                        }
-                        _ => {
-                            println!("Unhandled entity {}", entity_name.as_str());
-                            continue;
-                        }
-                    };
-                    collections.push(Collection {
-                        id,
-                        collection_type,
-                        parent,
-                        content: None,
-                    });
-                }
+        let mut stmt = conn.prepare(&schema.query(Query::Collections)?)?;
+        let mut rows = stmt.query(params![])?;
+        while let Some(row) = rows.next()? {
+            let entity: CoId = row.get(0)?;
+            // Keep the collection as `Other` if the entity is unknown.
+            let entity_name = entities.get(&entity).cloned().unwrap_or_else(|| {
+                log::warn!("Collection has unknown entity {}", entity);
+                format!("entity {entity}")
+            });
+            match Collection::from_row(row, &entity_name) {
+                Ok(collection) => collections.push(collection),
+                Err(err) => report.skip("ZCOLLECTION", row.get(1)?, row, err)?,
             }
         }
 
-        collections
+        Ok(collections)
     }
 
-    pub fn get_content(&mut self, conn: &rusqlite::Connection) {
-        let mut ids: Vec<CoId> = vec![];
-        if let Ok(mut stmt) = conn.prepare("SELECT Z_PK FROM ZSTACK WHERE ZCOLLECTION=?1") {
-            let mut rows = stmt.query([&self.id]).unwrap();
-            while let Ok(Some(row)) = rows.next() {
-                ids.push(row.get(0).unwrap());
+    /// Load the content of all the `collections` in one query.
+    pub(crate) fn load_content(
+        conn: &rusqlite::Connection,
+        schema: &Schema,
+        collections: &mut [Collection],
+        report: &mut LoadReport,
+    ) -> Result<()> {
+        let mut content: HashMap<CoId, Vec<CoId>> = HashMap::new();
+        let mut stmt = conn.prepare(&schema.query(Query::CollectionContent)?)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            // Stacks not in a collection are skipped.
+            match (row.get::<usize, Option<CoId>>(0), row.get(1)) {
+                (Ok(Some(collection)), Ok(stack)) => {
+                    content.entry(collection).or_default().push(stack)
+                }
+                (Ok(None), Ok(_)) => {}
+                (Err(err), _) | (_, Err(err)) => {
+                    report.skip("ZSTACK", row.get(1).unwrap_or(0), row, err)?
+                }
             }
-            self.content = Some(ids);
         }
+        for collection in collections.iter_mut() {
+            collection.content = Some(content.remove(&collection.id).unwrap_or_default());
+        }
+        Ok(())
     }
 }
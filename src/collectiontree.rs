@@ -0,0 +1,208 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::collections::{HashMap, HashSet};
+
+use super::{CoId, Collection};
+
+/// Collection tree
+/// The children are ordered by ZCOLLECTIONINDX.
+#[derive(Default)]
+pub struct CollectionTree {
+    root: CoId,
+    trash: Option<CoId>,
+    // Key is the parent id. Values: the children ids.
+    children: HashMap<CoId, Vec<CoId>>,
+    parents: HashMap<CoId, CoId>,
+    // Path component for each collection.
+    names: HashMap<CoId, String>,
+}
+
+impl CollectionTree {
+    /// Build the tree of `collections` under `root`.
+    pub fn new(root: CoId, collections: &[Collection]) -> CollectionTree {
+        let mut siblings: HashMap<CoId, Vec<&Collection>> = HashMap::new();
+        for collection in collections {
+            siblings
+                .entry(collection.parent)
+                .or_default()
+                .push(collection);
+        }
+        let children = siblings
+            .into_iter()
+            .map(|(parent, mut children)| {
+                children.sort_by_key(|collection| (collection.index, collection.id));
+                (
+                    parent,
+                    children.iter().map(|collection| collection.id).collect(),
+                )
+            })
+            .collect();
+        let trash = collections
+            .iter()
+            .find(|collection| collection.id == root)
+            .and_then(|collection| collection.trash);
+
+        CollectionTree {
+            root,
+            trash,
+            children,
+            parents: collections
+                .iter()
+                .map(|collection| (collection.id, collection.parent))
+                .collect(),
+            names: collections
+                .iter()
+                .map(|collection| {
+                    let name = collection
+                        .collection_type
+                        .name()
+                        .map(String::from)
+                        .unwrap_or_else(|| collection.collection_type.to_string());
+                    (collection.id, name)
+                })
+                .collect(),
+        }
+    }
+
+    pub fn root(&self) -> CoId {
+        self.root
+    }
+
+    /// The trash collection, from the root ZTRASHCOLLECTION.
+    pub fn trash(&self) -> Option<CoId> {
+        self.trash
+    }
+
+    /// Get children for collection with `id`, in order.
+    pub fn children_for(&self, id: CoId) -> Vec<CoId> {
+        self.children.get(&id).cloned().unwrap_or_default()
+    }
+
+    pub fn parent_of(&self, id: CoId) -> Option<CoId> {
+        self.parents.get(&id).copied().filter(|parent| *parent != 0)
+    }
+
+    /// The ancestors of `id`, from the parent up to the root.
+    pub fn ancestors(&self, id: CoId) -> Vec<CoId> {
+        let mut ancestors = vec![];
+        let mut seen = HashSet::from([id]);
+        let mut current = id;
+        while current != self.root {
+            match self.parent_of(current) {
+                Some(parent) if seen.insert(parent) => {
+                    ancestors.push(parent);
+                    current = parent;
+                }
+                _ => break,
+            }
+        }
+        ancestors
+    }
+
+    /// The descendants of `id`, depth first, in order.
+    pub fn descendants(&self, id: CoId) -> Vec<CoId> {
+        let mut descendants = vec![];
+        let mut seen = HashSet::from([id]);
+        let mut stack = self.children_for(id);
+        stack.reverse();
+        while let Some(current) = stack.pop() {
+            if !seen.insert(current) {
+                continue;
+            }
+            descendants.push(current);
+            stack.extend(self.children_for(current).into_iter().rev());
+        }
+        descendants
+    }
+
+    /// Whether `id` is the trash or inside it.
+    pub fn is_in_trash(&self, id: CoId) -> bool {
+        self.trash
+            .map(|trash| id == trash || self.ancestors(id).contains(&trash))
+            .unwrap_or(false)
+    }
+
+    /// The path of `id` from the root, like "Projects/2023/Wedding".
+    pub fn path(&self, id: CoId) -> String {
+        if id == self.root {
+            return String::new();
+        }
+        let mut components = self
+            .ancestors(id)
+            .into_iter()
+            .filter(|ancestor| *ancestor != self.root)
+            .rev()
+            .chain(std::iter::once(id))
+            .map(|id| self.names.get(&id).map(String::as_str).unwrap_or(""))
+            .collect::<Vec<_>>();
+        components.retain(|name| !name.is_empty());
+        components.join("/")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::CollectionTree;
+    use crate::{CoId, Collection, CollectionType};
+
+    fn collection(
+        id: CoId,
+        collection_type: CollectionType,
+        parent: CoId,
+        index: i64,
+    ) -> Collection {
+        Collection {
+            id,
+            collection_type,
+            parent,
+            index,
+            trash: None,
+            date_created: None,
+            date_modified: None,
+            sort_order: None,
+            primary_variant: None,
+            content: None,
+        }
+    }
+
+    #[test]
+    fn test_collection_tree() {
+        let mut root = collection(1, CollectionType::Project, 0, 0);
+        root.trash = Some(3);
+        let collections = vec![
+            root,
+            collection(2, CollectionType::CatalogAll, 1, 1),
+            collection(3, CollectionType::Trash, 1, 2),
+            collection(4, CollectionType::VirtualFolder("Projects".into()), 1, 0),
+            collection(5, CollectionType::VirtualFolder("2023".into()), 4, 1),
+            collection(6, CollectionType::Album("Wedding".into()), 5, 0),
+            collection(7, CollectionType::VirtualFolder("2022".into()), 4, 0),
+            collection(8, CollectionType::Album("Deleted".into()), 3, 0),
+        ];
+        let tree = CollectionTree::new(1, &collections);
+
+        assert_eq!(tree.root(), 1);
+        assert_eq!(tree.trash(), Some(3));
+        assert_eq!(tree.children_for(1), vec![4, 2, 3]);
+        assert_eq!(tree.children_for(4), vec![7, 5]);
+        assert!(tree.children_for(6).is_empty());
+
+        assert_eq!(tree.parent_of(6), Some(5));
+        assert_eq!(tree.parent_of(1), None);
+        assert_eq!(tree.ancestors(6), vec![5, 4, 1]);
+        assert_eq!(tree.descendants(4), vec![7, 5, 6]);
+        assert_eq!(tree.descendants(1), vec![4, 7, 5, 6, 2, 3, 8]);
+
+        assert_eq!(tree.path(6), "Projects/2023/Wedding");
+        assert_eq!(tree.path(3), "Trash");
+        assert_eq!(tree.path(1), "");
+
+        assert!(tree.is_in_trash(8));
+        assert!(tree.is_in_trash(3));
+        assert!(!tree.is_in_trash(6));
+    }
+}
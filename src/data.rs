@@ -0,0 +1,400 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::Deref;
+
+use super::{CoId, Collection, CollectionTree, Folder, Folders, Image, Keyword, KeywordTree};
+use super::{KeywordAssignments, Stack, Variant, VariantMetadata};
+
+/// Build the index id -> position for `items`.
+fn index<T>(items: &[T], id: impl Fn(&T) -> CoId) -> HashMap<CoId, usize> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(idx, item)| (id(item), idx))
+        .collect()
+}
+
+/// Build the index key -> positions in `items`.
+fn multi_index<T>(items: &[T], keys: impl Fn(&T) -> Vec<CoId>) -> HashMap<CoId, Vec<usize>> {
+    let mut index: HashMap<CoId, Vec<usize>> = HashMap::new();
+    for (idx, item) in items.iter().enumerate() {
+        for key in keys(item) {
+            index.entry(key).or_default().push(idx);
+        }
+    }
+    index
+}
+
+/// A reference to an object of the `CatalogData`, to navigate its
+/// relationships. Dereference to the object.
+pub struct Ref<'a, T> {
+    data: &'a CatalogData,
+    object: &'a T,
+}
+
+impl<T> Clone for Ref<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Ref<'_, T> {}
+
+impl<'a, T> Ref<'a, T> {
+    /// The object, with the lifetime of the data.
+    pub fn get(&self) -> &'a T {
+        self.object
+    }
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.object
+    }
+}
+
+impl<'a> Ref<'a, Image> {
+    /// The folder containing the image.
+    pub fn folder_of(&self) -> Option<Ref<'a, Folder>> {
+        self.data.folder(self.folder)
+    }
+
+    /// The variants of the image: clones and virtual copies.
+    pub fn variants(&self) -> Vec<Ref<'a, Variant>> {
+        self.data
+            .refs(&self.data.variants, self.data.image_variants.get(&self.id))
+    }
+
+    /// The stacks the image is part of.
+    pub fn stacks(&self) -> Vec<Ref<'a, Stack>> {
+        self.data
+            .refs(&self.data.stacks, self.data.image_stacks.get(&self.id))
+    }
+}
+
+impl<'a> Ref<'a, Variant> {
+    /// The image this is a variant of.
+    pub fn image_of(&self) -> Option<Ref<'a, Image>> {
+        self.data.image(self.image)
+    }
+
+    /// The user metadata of the variant.
+    pub fn metadata(&self) -> Option<&'a VariantMetadata> {
+        self.data
+            .variant_metadata_index
+            .get(&self.id)
+            .map(|idx| &self.data.variant_metadata[*idx])
+    }
+}
+
+impl<'a> Ref<'a, Stack> {
+    /// The images of the stack. Empty if the content wasn't loaded.
+    pub fn images(&self) -> Vec<Ref<'a, Image>> {
+        self.content
+            .iter()
+            .flatten()
+            .filter_map(|id| self.data.image(*id))
+            .collect()
+    }
+
+    /// The picked image of the stack.
+    pub fn pick_of(&self) -> Option<Ref<'a, Image>> {
+        self.data.image(self.pick)
+    }
+
+    /// The collection containing the stack.
+    pub fn collection_of(&self) -> Option<Ref<'a, Collection>> {
+        self.data.collection(self.collection)
+    }
+}
+
+impl<'a> Ref<'a, Folder> {
+    /// The images in the folder.
+    pub fn images(&self) -> Vec<Ref<'a, Image>> {
+        self.data
+            .refs(&self.data.images, self.data.folder_images.get(&self.id()))
+    }
+}
+
+impl<'a> Ref<'a, Collection> {
+    /// The parent collection.
+    pub fn parent_of(&self) -> Option<Ref<'a, Collection>> {
+        self.data.collection(self.parent)
+    }
+
+    /// The child collections, in order.
+    pub fn children(&self) -> Vec<Ref<'a, Collection>> {
+        self.data
+            .collection_tree
+            .children_for(self.id)
+            .into_iter()
+            .filter_map(|id| self.data.collection(id))
+            .collect()
+    }
+
+    /// The stacks of the collection. Empty if the content wasn't loaded.
+    pub fn stacks(&self) -> Vec<Ref<'a, Stack>> {
+        self.content
+            .iter()
+            .flatten()
+            .filter_map(|id| self.data.stack(*id))
+            .collect()
+    }
+}
+
+/// All the content loaded from a catalog. It doesn't hold on the
+/// catalog and is immutable, so it can be shared across threads.
+#[derive(Default)]
+pub struct CatalogData {
+    pub root_collection_id: CoId,
+    keywords: BTreeMap<CoId, Keyword>,
+    keyword_tree: KeywordTree,
+    folders: Folders,
+    images: Vec<Image>,
+    stacks: Vec<Stack>,
+    collections: Vec<Collection>,
+    collection_tree: CollectionTree,
+    variants: Vec<Variant>,
+    variant_metadata: Vec<VariantMetadata>,
+    keyword_assignments: KeywordAssignments,
+    /// Indexes: id -> position in the Vec.
+    folders_index: HashMap<CoId, usize>,
+    images_index: HashMap<CoId, usize>,
+    stacks_index: HashMap<CoId, usize>,
+    collections_index: HashMap<CoId, usize>,
+    variants_index: HashMap<CoId, usize>,
+    /// Variant id -> position.
+    variant_metadata_index: HashMap<CoId, usize>,
+    /// Reverse indexes: id -> positions in the Vec.
+    image_stacks: HashMap<CoId, Vec<usize>>,
+    folder_images: HashMap<CoId, Vec<usize>>,
+    image_variants: HashMap<CoId, Vec<usize>>,
+    /// Keyword id -> images, directly or through a variant.
+    keyword_images: HashMap<CoId, BTreeSet<CoId>>,
+}
+
+/// The content loaded by the `Catalog`, to build the `CatalogData`.
+pub(crate) struct Content {
+    pub root_collection_id: CoId,
+    pub keywords: BTreeMap<CoId, Keyword>,
+    pub keyword_tree: KeywordTree,
+    pub folders: Folders,
+    pub images: Vec<Image>,
+    pub stacks: Vec<Stack>,
+    pub collections: Vec<Collection>,
+    pub variants: Vec<Variant>,
+    pub variant_metadata: Vec<VariantMetadata>,
+    pub keyword_assignments: KeywordAssignments,
+}
+
+impl CatalogData {
+    pub(crate) fn new(content: Content) -> CatalogData {
+        let Content {
+            root_collection_id,
+            keywords,
+            keyword_tree,
+            folders,
+            images,
+            stacks,
+            collections,
+            variants,
+            variant_metadata,
+            keyword_assignments,
+        } = content;
+        let mut keyword_images: HashMap<CoId, BTreeSet<CoId>> = HashMap::new();
+        let variant_images = variants
+            .iter()
+            .map(|variant| (variant.id, variant.image))
+            .collect::<HashMap<_, _>>();
+        for (image, keywords) in &keyword_assignments.images {
+            for keyword in keywords {
+                keyword_images.entry(*keyword).or_default().insert(*image);
+            }
+        }
+        for (variant, keywords) in &keyword_assignments.variants {
+            if let Some(image) = variant_images.get(variant) {
+                for keyword in keywords {
+                    keyword_images.entry(*keyword).or_default().insert(*image);
+                }
+            }
+        }
+        CatalogData {
+            root_collection_id,
+            folders_index: index(&folders, Folder::id),
+            images_index: index(&images, |image| image.id),
+            stacks_index: index(&stacks, |stack| stack.id),
+            collections_index: index(&collections, |collection| collection.id),
+            image_stacks: multi_index(&stacks, |stack| stack.content.clone().unwrap_or_default()),
+            folder_images: multi_index(&images, |image| vec![image.folder]),
+            variants_index: index(&variants, |variant| variant.id),
+            variant_metadata_index: index(&variant_metadata, |metadata| metadata.variant),
+            image_variants: multi_index(&variants, |variant| vec![variant.image]),
+            collection_tree: CollectionTree::new(root_collection_id, &collections),
+            keywords,
+            keyword_tree,
+            folders,
+            images,
+            stacks,
+            collections,
+            variants,
+            variant_metadata,
+            keyword_assignments,
+            keyword_images,
+        }
+    }
+
+    pub fn keywords(&self) -> &BTreeMap<CoId, Keyword> {
+        &self.keywords
+    }
+
+    pub fn keyword_tree(&self) -> &KeywordTree {
+        &self.keyword_tree
+    }
+
+    pub fn folders(&self) -> &[Folder] {
+        &self.folders
+    }
+
+    pub fn images(&self) -> &[Image] {
+        &self.images
+    }
+
+    pub fn stacks(&self) -> &[Stack] {
+        &self.stacks
+    }
+
+    pub fn collections(&self) -> &[Collection] {
+        &self.collections
+    }
+
+    pub fn variants(&self) -> &[Variant] {
+        &self.variants
+    }
+
+    pub fn collection_tree(&self) -> &CollectionTree {
+        &self.collection_tree
+    }
+
+    pub fn keyword(&self, id: CoId) -> Option<&Keyword> {
+        self.keywords.get(&id)
+    }
+
+    pub fn folder(&self, id: CoId) -> Option<Ref<'_, Folder>> {
+        self.lookup(&self.folders, &self.folders_index, id)
+    }
+
+    pub fn image(&self, id: CoId) -> Option<Ref<'_, Image>> {
+        self.lookup(&self.images, &self.images_index, id)
+    }
+
+    pub fn stack(&self, id: CoId) -> Option<Ref<'_, Stack>> {
+        self.lookup(&self.stacks, &self.stacks_index, id)
+    }
+
+    pub fn collection(&self, id: CoId) -> Option<Ref<'_, Collection>> {
+        self.lookup(&self.collections, &self.collections_index, id)
+    }
+
+    pub fn variant(&self, id: CoId) -> Option<Ref<'_, Variant>> {
+        self.lookup(&self.variants, &self.variants_index, id)
+    }
+
+    /// The primary variant of image `id`, which carry the curation:
+    /// the one with the lowest id, created with the image before any
+    /// clone or virtual copy.
+    pub fn primary_variant(&self, id: CoId) -> Option<Ref<'_, Variant>> {
+        self.image_variants
+            .get(&id)
+            .and_then(|positions| positions.first())
+            .map(|idx| Ref {
+                data: self,
+                object: &self.variants[*idx],
+            })
+    }
+
+    /// The keywords assigned to variant `id`. With `expand`, their
+    /// ancestors are added.
+    pub fn keywords_for_variant(&self, id: CoId, expand: bool) -> Vec<CoId> {
+        let keywords = self.keyword_assignments.variants.get(&id);
+        self.expand_keywords(keywords.into_iter().flatten().copied(), expand)
+    }
+
+    /// The keywords assigned to image `id` or one of its variants.
+    /// With `expand`, their ancestors are added.
+    pub fn keywords_for_image(&self, id: CoId, expand: bool) -> Vec<CoId> {
+        let variants = self.image_variants.get(&id).into_iter().flatten();
+        let keywords = self
+            .keyword_assignments
+            .images
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .chain(variants.flat_map(|idx| {
+                self.keyword_assignments
+                    .variants
+                    .get(&self.variants[*idx].id)
+                    .into_iter()
+                    .flatten()
+            }))
+            .copied();
+        self.expand_keywords(keywords, expand)
+    }
+
+    fn expand_keywords(&self, keywords: impl Iterator<Item = CoId>, expand: bool) -> Vec<CoId> {
+        let mut result = BTreeSet::new();
+        for keyword in keywords {
+            result.insert(keyword);
+            if expand {
+                result.extend(self.keyword_tree.ancestors(keyword));
+            }
+        }
+        result.into_iter().collect()
+    }
+
+    /// The images with `keyword`, directly or through a variant. With
+    /// `expand`, the images with a descendant of `keyword` too.
+    pub fn images_with(&self, keyword: CoId, expand: bool) -> Vec<CoId> {
+        let mut keywords = vec![keyword];
+        if expand {
+            keywords.extend(self.keyword_tree.descendants(keyword));
+        }
+        keywords
+            .iter()
+            .filter_map(|keyword| self.keyword_images.get(keyword))
+            .flatten()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    fn lookup<'a, T>(
+        &'a self,
+        items: &'a [T],
+        index: &HashMap<CoId, usize>,
+        id: CoId,
+    ) -> Option<Ref<'a, T>> {
+        index.get(&id).map(|idx| Ref {
+            data: self,
+            object: &items[*idx],
+        })
+    }
+
+    fn refs<'a, T>(&'a self, items: &'a [T], positions: Option<&Vec<usize>>) -> Vec<Ref<'a, T>> {
+        positions
+            .into_iter()
+            .flatten()
+            .map(|idx| Ref {
+                data: self,
+                object: &items[*idx],
+            })
+            .collect()
+    }
+}
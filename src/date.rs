@@ -0,0 +1,225 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+
+use super::{Error, Result};
+
+/// Seconds between the Unix epoch and the Core Data reference date
+/// (2001-01-01 00:00:00 UTC).
+pub(crate) const CORE_DATA_EPOCH: f64 = 978_307_200.0;
+
+/// A Core Data timestamp: the seconds since 2001-01-01 00:00:00 UTC.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct CoDate(f64);
+
+impl CoDate {
+    pub fn new(timestamp: f64) -> CoDate {
+        CoDate(timestamp)
+    }
+
+    pub fn from_unix_timestamp(timestamp: f64) -> CoDate {
+        CoDate(timestamp - CORE_DATA_EPOCH)
+    }
+
+    /// The Core Data timestamp.
+    pub fn timestamp(&self) -> f64 {
+        self.0
+    }
+
+    /// The seconds since the Unix epoch.
+    pub fn unix_timestamp(&self) -> f64 {
+        self.0 + CORE_DATA_EPOCH
+    }
+
+    /// The milliseconds since the Unix epoch. `None` if it doesn't
+    /// fit.
+    fn unix_millis(&self) -> Option<i64> {
+        let millis = (self.unix_timestamp() * 1000.0).round();
+        (millis.is_finite() && millis.abs() < i64::MAX as f64).then_some(millis as i64)
+    }
+
+    /// Format as RFC 3339, in UTC. The milliseconds are only
+    /// written if not 0. `None` if the date isn't finite or is out of
+    /// range.
+    pub fn to_rfc3339(&self) -> Option<String> {
+        let millis = self.unix_millis()?;
+        let (secs, millis) = (millis.div_euclid(1000), millis.rem_euclid(1000));
+        let (days, secs) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+        let (year, month, day) = civil_from_days(days);
+        let mut date = format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            secs / 3600,
+            secs % 3600 / 60,
+            secs % 60
+        );
+        if millis != 0 {
+            date.push_str(&format!(".{:03}", millis));
+        }
+        date.push('Z');
+        Some(date)
+    }
+}
+
+/// Convert days since the Unix epoch to (year, month, day), in the
+/// proleptic Gregorian calendar.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's civil_from_days algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+impl fmt::Display for CoDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_rfc3339() {
+            Some(date) => f.pad(&date),
+            None => f.pad("invalid date"),
+        }
+    }
+}
+
+impl From<SystemTime> for CoDate {
+    fn from(time: SystemTime) -> CoDate {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(duration) => duration.as_secs_f64(),
+            Err(err) => -err.duration().as_secs_f64(),
+        };
+        CoDate::from_unix_timestamp(secs)
+    }
+}
+
+impl TryFrom<CoDate> for SystemTime {
+    type Error = Error;
+
+    fn try_from(date: CoDate) -> Result<SystemTime> {
+        let secs = date.unix_timestamp();
+        let duration = Duration::try_from_secs_f64(secs.abs())
+            .map_err(|_| Error::DateOutOfRange(date.timestamp()))?;
+        if secs >= 0.0 {
+            UNIX_EPOCH.checked_add(duration)
+        } else {
+            UNIX_EPOCH.checked_sub(duration)
+        }
+        .ok_or(Error::DateOutOfRange(date.timestamp()))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<CoDate> for chrono::DateTime<chrono::Utc> {
+    type Error = Error;
+
+    fn try_from(date: CoDate) -> Result<chrono::DateTime<chrono::Utc>> {
+        date.unix_millis()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .ok_or(Error::DateOutOfRange(date.timestamp()))
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<CoDate> for time::OffsetDateTime {
+    type Error = Error;
+
+    fn try_from(date: CoDate) -> Result<time::OffsetDateTime> {
+        date.unix_millis()
+            .and_then(|millis| {
+                time::OffsetDateTime::from_unix_timestamp_nanos(millis as i128 * 1_000_000).ok()
+            })
+            .ok_or(Error::DateOutOfRange(date.timestamp()))
+    }
+}
+
+impl FromSql for CoDate {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        match value {
+            ValueRef::Real(r) => Ok(CoDate(r)),
+            ValueRef::Integer(i) => Ok(CoDate(i as f64)),
+            _ => Err(FromSqlError::InvalidType),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::CoDate;
+
+    #[test]
+    fn test_date() {
+        let date = CoDate::new(0.0);
+        assert_eq!(date.unix_timestamp(), 978_307_200.0);
+        assert_eq!(date.to_rfc3339().as_deref(), Some("2001-01-01T00:00:00Z"));
+        assert_eq!(
+            CoDate::new(600086400.5).to_rfc3339().as_deref(),
+            Some("2020-01-07T10:40:00.500Z")
+        );
+        assert_eq!(
+            CoDate::from_unix_timestamp(0.0).to_string(),
+            "1970-01-01T00:00:00Z"
+        );
+        assert_eq!(
+            CoDate::from_unix_timestamp(951_827_696.0).to_string(),
+            "2000-02-29T12:34:56Z"
+        );
+        assert_eq!(
+            CoDate::from_unix_timestamp(-1.0).to_string(),
+            "1969-12-31T23:59:59Z"
+        );
+        assert!(CoDate::new(f64::NAN).to_rfc3339().is_none());
+        assert!(CoDate::new(1e300).to_rfc3339().is_none());
+        assert_eq!(CoDate::new(f64::INFINITY).to_string(), "invalid date");
+
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let date = CoDate::from(time);
+        assert_eq!(date.unix_timestamp(), 1_000_000_000.0);
+        assert_eq!(SystemTime::try_from(date).unwrap(), time);
+        assert!(SystemTime::try_from(CoDate::new(f64::NAN)).is_err());
+        assert!(SystemTime::try_from(CoDate::new(f64::INFINITY)).is_err());
+        assert!(SystemTime::try_from(CoDate::new(-1e300)).is_err());
+
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let date: CoDate = conn
+            .query_row("SELECT 700000000.5", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(date.timestamp(), 700_000_000.5);
+        assert!(conn
+            .query_row("SELECT 'today'", [], |row| row.get::<usize, CoDate>(0))
+            .is_err());
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono() {
+        let date = chrono::DateTime::<chrono::Utc>::try_from(CoDate::new(600086400.5)).unwrap();
+        assert_eq!(date.to_rfc3339(), "2020-01-07T10:40:00.500+00:00");
+        assert!(chrono::DateTime::<chrono::Utc>::try_from(CoDate::new(f64::NAN)).is_err());
+        assert!(chrono::DateTime::<chrono::Utc>::try_from(CoDate::new(1e300)).is_err());
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn test_time() {
+        let date = time::OffsetDateTime::try_from(CoDate::new(600086400.5)).unwrap();
+        assert_eq!(date.unix_timestamp(), 1578393600);
+        assert_eq!(date.millisecond(), 500);
+        assert!(time::OffsetDateTime::try_from(CoDate::new(f64::INFINITY)).is_err());
+        assert!(time::OffsetDateTime::try_from(CoDate::new(1e15)).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Synthetic catalogs for the tests.
+//!
+//! They only contain the tables and columns the crate reads, filled
+//! with a small, known, set of rows.
+
+use std::path::{Path, PathBuf};
+
+use rusqlite::{params, Connection};
+
+use crate::catalog::DB_FILENAME;
+
+const STORE_UUID: &str = "8C0A3B5E-2C1F-4E4B-9D3A-6F2E1A0B9C8D";
+
+const SCHEMA: &str = "
+CREATE TABLE Z_METADATA (Z_VERSION INTEGER PRIMARY KEY, Z_UUID VARCHAR(255), Z_PLIST BLOB);
+CREATE TABLE ZENTITIES (Z_ENT INTEGER PRIMARY KEY, ZNAME VARCHAR);
+CREATE TABLE ZVERSIONINFO (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZVERSION INTEGER,
+    ZCOMPATIBLEVERSION INTEGER, ZAUTHOR VARCHAR, ZFORMAT VARCHAR, ZCOMPATIBILITY VARCHAR);
+CREATE TABLE ZDOCUMENTCONTENT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZROOTCOLLECTION INTEGER);
+CREATE TABLE ZKEYWORD (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR, ZPARENT INTEGER);
+CREATE TABLE ZPATHLOCATION (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZMACROOT VARCHAR,
+    ZWINROOT VARCHAR, ZRELATIVEPATH VARCHAR, ZISRELATIVE INTEGER, ZVOLUME VARCHAR);
+CREATE TABLE ZIMAGE (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZIMAGEUUID VARCHAR,
+    ZIMAGELOCATION INTEGER, ZDISPLAYNAME VARCHAR, ZIMAGEFILENAME VARCHAR,
+    ZIMAGECLASSIFICATION INTEGER, ZEXP_FORMAT VARCHAR, ZEXP_CAPTUREDATE TIMESTAMP,
+    ZEXP_CAPTURETIMEZONE VARCHAR, ZEXP_CAMERAMAKE VARCHAR, ZEXP_CAMERAMODEL VARCHAR,
+    ZEXP_LENS VARCHAR, ZEXP_FOCALLENGTH FLOAT, ZEXP_APERTURE FLOAT, ZEXP_SHUTTERSPEED FLOAT,
+    ZEXP_ISO INTEGER, ZEXP_EXPOSURECOMPENSATION FLOAT, ZEXP_ORIENTATION INTEGER,
+    ZWIDTH INTEGER, ZHEIGHT INTEGER, ZFILE_SIZE INTEGER);
+CREATE TABLE ZSTACK (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZCOLLECTION INTEGER,
+    ZPICKEDIMAGE INTEGER);
+CREATE TABLE ZSTACKIMAGELINK (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZINDEX INTEGER,
+    ZIMAGE INTEGER, ZSTACK INTEGER);
+CREATE TABLE ZCOLLECTION (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZNAME VARCHAR,
+    ZPARENT INTEGER, ZFOLDERLOCATION INTEGER, ZTRASHCOLLECTION INTEGER,
+    ZCOLLECTIONINDX INTEGER, ZDATECREATED TIMESTAMP, ZDATEMODIFIED TIMESTAMP,
+    ZSORTORDER VARCHAR, ZPRIMARYVARIANT INTEGER);
+CREATE TABLE ZVARIANT (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, ZVARIANTUUID VARCHAR,
+    ZIMAGE INTEGER, ZPROCESSINGENGINE INTEGER, ZPRODUCTID VARCHAR, ZDEFAULTLAYER INTEGER,
+    ZADJUSTMENTLAYER INTEGER, ZCOMBINEDSETTINGS INTEGER, ZRATING INTEGER, ZCOLORTAG INTEGER,
+    ZPICKSTATE INTEGER);
+CREATE TABLE ZVARIANTMETADATA (Z_PK INTEGER PRIMARY KEY, Z_ENT INTEGER, Z_OPT INTEGER,
+    ZVARIANT INTEGER, ZTITLE VARCHAR, ZHEADLINE VARCHAR, ZCAPTION VARCHAR, ZCREATOR VARCHAR,
+    ZCOPYRIGHT VARCHAR, ZCITY VARCHAR, ZCOUNTRY VARCHAR, ZCUSTOMFIELD VARCHAR,
+    ZEXTRASCORE INTEGER);
+CREATE TABLE Z_14KEYWORDS (Z_14VARIANTS INTEGER, Z_3KEYWORDS INTEGER,
+    PRIMARY KEY (Z_14VARIANTS, Z_3KEYWORDS));
+CREATE TABLE Z_5KEYWORDS (Z_5IMAGES INTEGER, Z_3KEYWORDS INTEGER,
+    PRIMARY KEY (Z_5IMAGES, Z_3KEYWORDS));
+";
+
+const GPS_SCHEMA: &str = "
+ALTER TABLE ZIMAGE ADD COLUMN ZGPSALTITUDE FLOAT;
+ALTER TABLE ZIMAGE ADD COLUMN ZGPSLATITUDE FLOAT;
+ALTER TABLE ZIMAGE ADD COLUMN ZGPSLONGITUDE FLOAT;
+";
+
+const CONTENT: &str = "
+INSERT INTO ZENTITIES VALUES (1, 'VersionInfo'), (2, 'DocumentContent'), (3, 'Keyword'),
+    (4, 'PathLocation'), (5, 'Image'), (6, 'Stack'), (7, 'ProjectCollection'),
+    (8, 'CatalogAllImagesCollection'), (9, 'TrashCollection'),
+    (10, 'CatalogInternalImagesCollection'), (11, 'AlbumCollection'),
+    (12, 'CatalogFolderCollection'), (13, 'VirtualFolderCollection'), (14, 'Variant'),
+    (15, 'VariantMetadata');
+INSERT INTO ZDOCUMENTCONTENT VALUES (1, 2, 1);
+INSERT INTO ZKEYWORD VALUES (1, 3, 'places', NULL), (2, 3, 'Montréal', 1), (3, 3, 'people', NULL);
+INSERT INTO ZPATHLOCATION VALUES (1, 4, '/Volumes/Photos', '', '2019/', 0, 'Photos'),
+    (2, 4, '', '', 'Originals/', 1, '');
+INSERT INTO ZIMAGE (Z_PK, Z_ENT, ZIMAGEUUID, ZIMAGELOCATION, ZDISPLAYNAME, ZIMAGEFILENAME,
+    ZIMAGECLASSIFICATION, ZEXP_FORMAT) VALUES
+    (1, 5, '0B5A13A6-6D45-4E0A-9D4B-C1E0C0D0E0F1', 1, 'DSCF0001', 'DSCF0001.RAF', 17, 'RAW'),
+    (2, 5, '1C6B24B7-7E56-5F1B-AE5C-D2F1D1E1F1A2', 1, 'DSCF0001', 'DSCF0001.JPG', 19, 'JPEG'),
+    (3, 5, '2D7C35C8-8F67-6A2C-BF6D-E3A2E2F2A2B3', 2, 'DSCF0002', 'DSCF0002.MOV', 6, 'MOVIE');
+UPDATE ZIMAGE SET ZEXP_CAPTUREDATE = 600000000.0 WHERE Z_PK IN (1, 2);
+UPDATE ZIMAGE SET ZEXP_CAPTURETIMEZONE = '-05:00', ZEXP_CAMERAMAKE = 'FUJIFILM',
+    ZEXP_CAMERAMODEL = 'X-T3', ZEXP_LENS = 'XF23mmF2 R WR', ZEXP_FOCALLENGTH = 23,
+    ZEXP_APERTURE = 5.6, ZEXP_SHUTTERSPEED = 0.004, ZEXP_ISO = 160,
+    ZEXP_EXPOSURECOMPENSATION = -0.67, ZEXP_ORIENTATION = 6, ZWIDTH = 6240,
+    ZHEIGHT = 4160, ZFILE_SIZE = 51234567 WHERE Z_PK = 1;
+INSERT INTO ZCOLLECTION (Z_PK, Z_ENT, ZNAME, ZPARENT, ZFOLDERLOCATION, ZTRASHCOLLECTION,
+    ZCOLLECTIONINDX) VALUES (1, 7, NULL, NULL, NULL, 3, NULL),
+    (2, 8, NULL, 1, NULL, NULL, 0), (3, 9, NULL, 1, NULL, NULL, 5),
+    (4, 10, NULL, 1, NULL, NULL, 1), (5, 13, 'Projects', 1, NULL, NULL, 2),
+    (6, 11, 'Best of', 5, NULL, NULL, 0), (7, 12, NULL, 1, 1, NULL, 4),
+    (8, 12, NULL, 1, 2, NULL, 3);
+UPDATE ZCOLLECTION SET ZDATECREATED = 600000000.0, ZDATEMODIFIED = 600086400.5,
+    ZSORTORDER = 'captureDate', ZPRIMARYVARIANT = 2 WHERE Z_PK = 6;
+INSERT INTO ZSTACK VALUES (1, 6, 2, 1), (2, 6, 2, 3), (3, 6, 6, 1);
+INSERT INTO ZSTACKIMAGELINK VALUES (1, NULL, 0, 1, 1), (2, NULL, 1, 2, 1), (3, NULL, 0, 3, 2),
+    (4, NULL, 0, 1, 3);
+INSERT INTO ZVARIANT (Z_PK, Z_ENT, ZVARIANTUUID, ZIMAGE, ZPROCESSINGENGINE, ZPRODUCTID,
+    ZDEFAULTLAYER, ZADJUSTMENTLAYER, ZCOMBINEDSETTINGS) VALUES
+    (1, 14, 'A1B2C3D4-0000-4000-8000-000000000001', 1, 1200, 'FujiXT3', 1, 2, 3),
+    (2, 14, 'A1B2C3D4-0000-4000-8000-000000000002', 2, 1200, 'FileSystemJpeg', 4, 5, 6),
+    (3, 14, 'A1B2C3D4-0000-4000-8000-000000000003', 3, 1100, NULL, NULL, NULL, NULL),
+    (4, 14, 'A1B2C3D4-0000-4000-8000-000000000004', 1, 1300, 'FujiXT3', 7, 8, 9);
+UPDATE ZVARIANT SET ZRATING = 4, ZCOLORTAG = 4, ZPICKSTATE = 1 WHERE Z_PK = 1;
+UPDATE ZVARIANT SET ZRATING = 2, ZPICKSTATE = -1 WHERE Z_PK = 2;
+INSERT INTO Z_14KEYWORDS VALUES (1, 2);
+INSERT INTO Z_5KEYWORDS VALUES (2, 3);
+INSERT INTO ZVARIANTMETADATA VALUES
+    (1, 15, 1, 1, 'Old port', NULL, 'The old port at dusk', 'Jane Doe', '© 2019', 'Montréal',
+        'Canada', 'Client A', 7),
+    (2, 15, 1, 2, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL);
+";
+
+/// Options for the synthetic catalog.
+pub struct Fixture {
+    /// The `ZVERSION` to record.
+    pub version: i32,
+    /// Whether to have the `ZVERSIONINFO` row.
+    pub version_info: bool,
+    /// Whether the GPS columns are in `ZIMAGE`.
+    pub gps: bool,
+}
+
+impl Fixture {
+    pub fn new(version: i32) -> Fixture {
+        Fixture {
+            version,
+            version_info: true,
+            gps: true,
+        }
+    }
+
+    /// Populate the database at `path`.
+    pub fn create(&self, path: &Path) {
+        let conn = Connection::open(path).expect("Couldn't create fixture");
+        // Like Core Data.
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        if self.gps {
+            conn.execute_batch(GPS_SCHEMA).unwrap();
+        }
+        conn.execute_batch(CONTENT).unwrap();
+        if self.gps {
+            conn.execute(
+                "UPDATE ZIMAGE SET ZGPSALTITUDE=30.5, ZGPSLATITUDE=45.5, ZGPSLONGITUDE=-73.6 WHERE Z_PK=1",
+                [],
+            )
+            .unwrap();
+        }
+        if self.version_info {
+            // The catalog was created by an older version.
+            conn.execute(
+                "INSERT INTO ZVERSIONINFO VALUES (1, 1, 1106, 1106, '11.3.0.24', '11.3.0.24', NULL)",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "INSERT INTO ZVERSIONINFO VALUES (2, 1, ?1, 1106, ?2, ?2, 'Compatible')",
+                params![self.version, format!("{}.0.0.1", self.version / 100)],
+            )
+            .unwrap();
+        }
+        conn.execute(
+            "INSERT INTO Z_METADATA VALUES (1, ?1, ?2)",
+            params![STORE_UUID, self.store_metadata()],
+        )
+        .unwrap();
+    }
+
+    /// The binary plist for `Z_METADATA`.
+    fn store_metadata(&self) -> Vec<u8> {
+        let mut hashes = plist::Dictionary::new();
+        for entity in ["Image", "Keyword", "PathLocation", "Stack"] {
+            hashes.insert(
+                entity.into(),
+                plist::Value::Data(entity.as_bytes().to_vec()),
+            );
+        }
+        let mut dict = plist::Dictionary::new();
+        dict.insert("NSStoreUUID".into(), STORE_UUID.into());
+        dict.insert("NSStoreType".into(), "SQLite".into());
+        dict.insert(
+            "NSStoreModelVersionIdentifiers".into(),
+            plist::Value::Array(vec![self.version.to_string().into()]),
+        );
+        dict.insert("NSStoreModelVersionHashes".into(), hashes.into());
+        let mut data = vec![];
+        plist::to_writer_binary(&mut data, &plist::Value::Dictionary(dict)).unwrap();
+        data
+    }
+
+    /// Create a catalog bundle in `dir` and return its path.
+    pub fn create_bundle(&self, dir: &Path) -> PathBuf {
+        let bundle = dir.join("Test.cocatalog");
+        std::fs::create_dir_all(&bundle).unwrap();
+        self.create(&bundle.join(DB_FILENAME));
+        bundle
+    }
+}
@@ -4,11 +4,12 @@
  file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use super::CoId;
+use super::schema::{Query, Schema};
+use super::{CoId, LoadReport, Result};
 
 pub type Folders = Vec<Folder>;
 
-#[derive(Default)]
+#[derive(Clone, Default)]
 pub struct Folder {
     id: CoId,
     /// Indicate the path is relative to the catalog.
@@ -24,22 +25,31 @@ impl Folder {
         self.id
     }
 
-    pub fn load_objects(conn: &rusqlite::Connection, entity: CoId) -> Folders {
+    pub(crate) fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Folder> {
+        Ok(Folder {
+            id: row.get(0)?,
+            is_relative: row.get(3)?,
+            path_from_root: row.get(2)?,
+            root_folder: row.get(1)?,
+        })
+    }
+
+    pub(crate) fn load_objects(
+        conn: &rusqlite::Connection,
+        schema: &Schema,
+        entity: CoId,
+        report: &mut LoadReport,
+    ) -> Result<Folders> {
         let mut folders: Folders = vec![];
 
-        if let Ok(mut stmt) = conn.prepare(
-            "SELECT Z_PK, ZMACROOT, ZRELATIVEPATH, ZISRELATIVE FROM ZPATHLOCATION WHERE Z_ENT=?1",
-        ) {
-            let mut rows = stmt.query([&entity]).unwrap();
-            while let Ok(Some(row)) = rows.next() {
-                folders.push(Folder {
-                    id: row.get(0).unwrap(),
-                    is_relative: row.get(3).unwrap(),
-                    path_from_root: row.get(2).unwrap(),
-                    root_folder: row.get(1).unwrap(),
-                });
+        let mut stmt = conn.prepare(&schema.query(Query::Folders)?)?;
+        let mut rows = stmt.query([&entity])?;
+        while let Some(row) = rows.next()? {
+            match Folder::from_row(row) {
+                Ok(folder) => folders.push(folder),
+                Err(err) => report.skip("ZPATHLOCATION", row.get(0)?, row, err)?,
             }
         }
-        folders
+        Ok(folders)
     }
 }
@@ -6,7 +6,8 @@
 
 use std::fmt;
 
-use super::CoId;
+use super::schema::{Query, Schema, IMAGES_CAPTURE_INFO};
+use super::{CaptureInfo, CoId, LoadReport, Result};
 
 #[derive(Debug, Default)]
 pub enum ImageFormat {
@@ -51,29 +52,44 @@ pub struct Image {
     pub gps_alt: Option<f64>,
     pub gps_lat: Option<f64>,
     pub gps_long: Option<f64>,
+    pub capture: CaptureInfo,
 }
 
 impl Image {
-    pub fn load_objects(conn: &rusqlite::Connection, entity: CoId) -> Vec<Image> {
+    /// The optional columns are read leniently: a value of the wrong
+    /// type is `None`.
+    pub(crate) fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Image> {
+        Ok(Image {
+            id: row.get(0)?,
+            uuid: row.get(1)?,
+            folder: row.get(2)?,
+            display_name: row.get(3)?,
+            file_name: row.get(4)?,
+            class: row.get(5)?,
+            format: ImageFormat::from(row.get::<usize, String>(6)?.as_str()),
+            gps_alt: row.get(7).ok().flatten(),
+            gps_lat: row.get(8).ok().flatten(),
+            gps_long: row.get(9).ok().flatten(),
+            capture: CaptureInfo::from_row(row, IMAGES_CAPTURE_INFO),
+        })
+    }
+
+    pub(crate) fn load_objects(
+        conn: &rusqlite::Connection,
+        schema: &Schema,
+        entity: CoId,
+        report: &mut LoadReport,
+    ) -> Result<Vec<Image>> {
         let mut images: Vec<Image> = vec![];
-        if let Ok(mut stmt) = conn.prepare("SELECT Z_PK, ZIMAGEUUID, ZIMAGELOCATION, ZDISPLAYNAME, ZIMAGEFILENAME, ZIMAGECLASSIFICATION, ZEXP_FORMAT, ZGPSALTITUDE, ZGPSLATITUDE, ZGPSLONGITUDE FROM ZIMAGE WHERE Z_ENT=?1") {
-            let mut rows = stmt.query([&entity]).unwrap();
-            while let Ok(Some(row)) = rows.next() {
-                images.push(Image {
-                    id: row.get(0).unwrap(),
-                    uuid: row.get(1).unwrap(),
-                    folder: row.get(2).unwrap(),
-                    display_name: row.get(3).unwrap(),
-                    file_name: row.get(4).unwrap(),
-                    class: row.get(5).unwrap(),
-                    format: ImageFormat::from(row.get::<usize, String>(6).unwrap().as_str()),
-                    gps_alt: row.get::<usize, f64>(7).ok(),
-                    gps_lat: row.get::<usize, f64>(8).ok(),
-                    gps_long: row.get::<usize, f64>(9).ok()
-                });
+        let mut stmt = conn.prepare(&schema.query(Query::Images)?)?;
+        let mut rows = stmt.query([&entity])?;
+        while let Some(row) = rows.next()? {
+            match Image::from_row(row) {
+                Ok(image) => images.push(image),
+                Err(err) => report.skip("ZIMAGE", row.get(0)?, row, err)?,
             }
         }
 
-        images
+        Ok(images)
     }
 }
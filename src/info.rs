@@ -0,0 +1,80 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use super::schema::{Query, Schema};
+use super::{CoId, Result};
+
+/// A row of `ZVERSIONINFO`. A row is added each time a new version
+/// of Capture One opens the catalog.
+#[derive(Debug, Default)]
+pub struct VersionInfo {
+    pub id: CoId,
+    /// ZVERSION
+    pub version: i32,
+    /// ZCOMPATIBLEVERSION
+    pub compatible_version: Option<i32>,
+    /// ZAUTHOR: the app version string.
+    pub author: Option<String>,
+    /// ZFORMAT
+    pub format: Option<String>,
+    /// ZCOMPATIBILITY
+    pub compatibility: Option<String>,
+}
+
+/// The catalog information, from `ZVERSIONINFO` and `ZDOCUMENTCONTENT`.
+#[derive(Debug, Default)]
+pub struct CatalogInfo {
+    /// The version history, oldest first.
+    pub history: Vec<VersionInfo>,
+    /// The root collection from `ZDOCUMENTCONTENT`.
+    pub root_collection_id: CoId,
+}
+
+impl CatalogInfo {
+    pub(crate) fn load(conn: &rusqlite::Connection, schema: &Schema) -> Result<CatalogInfo> {
+        let mut info = CatalogInfo::default();
+
+        let mut stmt = conn.prepare(&schema.query(Query::VersionInfo)?)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            info.history.push(VersionInfo {
+                id: row.get(0)?,
+                version: row.get(1)?,
+                compatible_version: row.get(2)?,
+                author: row.get(3)?,
+                format: row.get(4)?,
+                compatibility: row.get(5)?,
+            });
+        }
+
+        let mut stmt = conn.prepare(&schema.query(Query::DocumentContent)?)?;
+        let mut rows = stmt.query([])?;
+        if let Some(row) = rows.next()? {
+            info.root_collection_id = row.get::<usize, Option<CoId>>(0)?.unwrap_or(0);
+        }
+
+        Ok(info)
+    }
+
+    /// The most recent version information.
+    pub fn current(&self) -> Option<&VersionInfo> {
+        self.history.last()
+    }
+
+    /// The version string of the app that last opened the catalog.
+    pub fn app_version(&self) -> Option<&str> {
+        self.current().and_then(|info| info.author.as_deref())
+    }
+
+    /// The oldest version the catalog is compatible with.
+    pub fn compatible_version(&self) -> Option<i32> {
+        self.current().and_then(|info| info.compatible_version)
+    }
+
+    pub fn format(&self) -> Option<&str> {
+        self.current().and_then(|info| info.format.as_deref())
+    }
+}
@@ -0,0 +1,149 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::collections::VecDeque;
+
+use rusqlite::{params, Connection};
+
+use super::schema::{Query, Schema};
+use super::{CoId, Folder, Image, LoadMode, LoadReport, LoadWarning, Result, Stack, Variant};
+
+/// An object that can be loaded from a row of its `Query`, with the
+/// `Z_PK` as the first column.
+pub(crate) trait FromRow: Sized {
+    const TABLE: &'static str;
+    const QUERY: Query;
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self>;
+}
+
+impl FromRow for Image {
+    const TABLE: &'static str = "ZIMAGE";
+    const QUERY: Query = Query::Images;
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Image> {
+        Image::from_row(row)
+    }
+}
+
+impl FromRow for Folder {
+    const TABLE: &'static str = "ZPATHLOCATION";
+    const QUERY: Query = Query::Folders;
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Folder> {
+        Folder::from_row(row)
+    }
+}
+
+impl FromRow for Stack {
+    const TABLE: &'static str = "ZSTACK";
+    const QUERY: Query = Query::Stacks;
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Stack> {
+        Stack::from_row(row)
+    }
+}
+
+impl FromRow for Variant {
+    const TABLE: &'static str = "ZVARIANT";
+    const QUERY: Query = Query::Variants;
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Variant> {
+        Variant::from_row(row)
+    }
+}
+
+/// The default number of rows fetched at once.
+const PAGE_SIZE: usize = 1000;
+
+/// Iterate over the objects of an entity, without loading them all
+/// in memory. Rows are fetched by pages, ordered by `Z_PK`.
+///
+/// Malformed rows follow the `LoadMode`: in lenient mode they are
+/// skipped and reported in `warnings()`, in strict mode they yield
+/// `Error::MalformedRow`, then the iteration continue.
+pub struct ObjectIter<'a, T> {
+    conn: &'a Connection,
+    sql: String,
+    entity: CoId,
+    /// Z_PK of the last row fetched.
+    last: CoId,
+    page_size: usize,
+    buffer: VecDeque<Result<T>>,
+    done: bool,
+    report: LoadReport,
+}
+
+impl<'a, T> ObjectIter<'a, T> {
+    pub(crate) fn new(
+        conn: &'a Connection,
+        schema: &Schema,
+        entity: CoId,
+        mode: LoadMode,
+    ) -> Result<Self>
+    where
+        T: FromRow,
+    {
+        Ok(ObjectIter {
+            conn,
+            sql: schema.paged_query(T::QUERY)?,
+            entity,
+            last: 0,
+            page_size: PAGE_SIZE,
+            buffer: VecDeque::new(),
+            done: false,
+            report: LoadReport::new(mode),
+        })
+    }
+
+    /// Set the number of rows fetched at once.
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// The warnings about the rows skipped so far.
+    pub fn warnings(&self) -> &[LoadWarning] {
+        self.report.warnings()
+    }
+
+    fn fetch_page(&mut self) -> Result<()>
+    where
+        T: FromRow,
+    {
+        let mut stmt = self.conn.prepare_cached(&self.sql)?;
+        let mut rows = stmt.query(params![self.entity, self.last, self.page_size as i64])?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            count += 1;
+            self.last = row.get(0)?;
+            match T::from_row(row) {
+                Ok(object) => self.buffer.push_back(Ok(object)),
+                Err(err) => {
+                    if let Err(err) = self.report.skip(T::TABLE, self.last, row, err) {
+                        self.buffer.push_back(Err(err));
+                    }
+                }
+            }
+        }
+        self.done = count < self.page_size;
+        Ok(())
+    }
+}
+
+impl<T: FromRow> Iterator for ObjectIter<'_, T> {
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        if self.buffer.is_empty() && !self.done {
+            if let Err(err) = self.fetch_page() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.buffer.pop_front()
+    }
+}
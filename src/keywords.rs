@@ -4,7 +4,10 @@
  file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use super::CoId;
+use std::collections::BTreeMap;
+
+use super::schema::{Query, Schema};
+use super::{CoId, LoadReport, Result};
 
 #[derive(Default, Debug)]
 pub struct Keyword {
@@ -25,4 +28,32 @@ impl Keyword {
     pub fn id(&self) -> CoId {
         self.id
     }
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Keyword> {
+        Ok(Keyword {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            parent: row.get::<usize, Option<CoId>>(2)?.unwrap_or(0),
+        })
+    }
+
+    pub(crate) fn load_objects(
+        conn: &rusqlite::Connection,
+        schema: &Schema,
+        entity: CoId,
+        report: &mut LoadReport,
+    ) -> Result<BTreeMap<CoId, Keyword>> {
+        let mut keywords = BTreeMap::new();
+        let mut stmt = conn.prepare(&schema.query(Query::Keywords)?)?;
+        let mut rows = stmt.query([entity])?;
+        while let Some(row) = rows.next()? {
+            match Keyword::from_row(row) {
+                Ok(keyword) => {
+                    keywords.insert(keyword.id(), keyword);
+                }
+                Err(err) => report.skip("ZKEYWORD", row.get(0)?, row, err)?,
+            }
+        }
+        Ok(keywords)
+    }
 }
@@ -4,7 +4,7 @@
  file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use super::Keyword;
 
@@ -14,6 +14,8 @@ use super::Keyword;
 pub struct KeywordTree {
     // HashMap. Key is the parent id. Values: the children ids.
     map: HashMap<i64, Vec<i64>>,
+    // Key is the child id. Value: the parent id.
+    parents: HashMap<i64, i64>,
 }
 
 impl KeywordTree {
@@ -29,7 +31,39 @@ impl KeywordTree {
         vec![]
     }
 
+    /// Get the ancestors of keyword with `id`, from the parent up.
+    pub fn ancestors(&self, id: i64) -> Vec<i64> {
+        let mut ancestors = vec![];
+        let mut seen = HashSet::from([id]);
+        let mut current = id;
+        while let Some(parent) = self.parents.get(&current) {
+            if *parent == 0 || !seen.insert(*parent) {
+                break;
+            }
+            ancestors.push(*parent);
+            current = *parent;
+        }
+        ancestors
+    }
+
+    /// Get the descendants of keyword with `id`, depth first.
+    pub fn descendants(&self, id: i64) -> Vec<i64> {
+        let mut descendants = vec![];
+        let mut seen = HashSet::from([id]);
+        let mut stack = self.children_for(id);
+        stack.reverse();
+        while let Some(current) = stack.pop() {
+            if !seen.insert(current) {
+                continue;
+            }
+            descendants.push(current);
+            stack.extend(self.children_for(current).into_iter().rev());
+        }
+        descendants
+    }
+
     pub fn add_child(&mut self, keyword: &Keyword) {
+        self.parents.insert(keyword.id(), keyword.parent);
         self.map.entry(keyword.parent).or_default();
         self.map
             .get_mut(&keyword.parent)
@@ -63,6 +97,10 @@ impl KeywordTree {
 
         let children = tree.children_for(0);
         assert_eq!(children, vec![1, 4]);
+
+        assert_eq!(tree.ancestors(3), vec![2, 1]);
+        assert!(tree.ancestors(4).is_empty());
+        assert_eq!(tree.descendants(1), vec![2, 3, 5]);
     }
 }
 
@@ -6,23 +6,52 @@
 
 extern crate rusqlite;
 
+mod assignments;
+mod captureinfo;
 mod catalog;
 mod collections;
+mod collectiontree;
+mod data;
+mod date;
+#[cfg(test)]
+mod fixtures;
 mod folders;
 mod images;
+mod info;
+mod iter;
 mod keywords;
 mod keywordtree;
+mod metadata;
+mod rating;
+mod report;
+mod schema;
+mod session;
 mod stack;
+mod variant;
+mod variantmetadata;
 
 use thiserror::Error;
 
+pub use assignments::KeywordAssignments;
+pub use captureinfo::CaptureInfo;
 pub use catalog::{Catalog, CatalogVersion};
 pub use collections::{Collection, CollectionType};
+pub use collectiontree::CollectionTree;
+pub use data::{CatalogData, Ref};
+pub use date::CoDate;
 pub use folders::{Folder, Folders};
 pub use images::Image;
+pub use info::{CatalogInfo, VersionInfo};
+pub use iter::ObjectIter;
 pub use keywords::Keyword;
 pub use keywordtree::KeywordTree;
+pub use metadata::StoreMetadata;
+pub use rating::{xmp_rating, ColorTag, PickState};
+pub use report::{LoadMode, LoadReport, LoadWarning};
+pub use session::{Session, SessionFolder};
 pub use stack::Stack;
+pub use variant::Variant;
+pub use variantmetadata::VariantMetadata;
 
 pub type CoId = i64;
 
@@ -31,12 +60,42 @@ pub enum Error {
     /// No database open.
     #[error("No database.")]
     NoDatabase,
+    /// The catalog is in use.
+    #[error("Co: Catalog in use.")]
+    CatalogInUse,
+    /// Several session databases and none named after the session.
+    #[error("Co: Several session databases.")]
+    AmbiguousSession,
     /// Unimplemented
     #[error("Unimplemented.")]
     Unimplemented,
     /// Unsupported catalog version.
     #[error("LrCat: Unsupported catalog version.")]
     UnsupportedVersion,
+    /// Core Data metadata Error.
+    #[error("Co: Metadata error: {0}.")]
+    Plist(#[from] plist::Error),
+    /// Backup archive Error.
+    #[error("Co: Archive error: {0}.")]
+    Archive(#[from] zip::result::ZipError),
+    /// IO Error.
+    #[error("Co: IO error: {0}.")]
+    Io(#[from] std::io::Error),
+    /// A table is missing from the database.
+    #[error("Co: Missing table {0}.")]
+    MissingTable(String),
+    /// A column is missing from a table.
+    #[error("Co: Missing column {1} in table {0}.")]
+    MissingColumn(String, String),
+    /// An entity is missing from ZENTITIES.
+    #[error("Co: Missing entity {0}.")]
+    MissingEntity(String),
+    /// A date that can't be represented.
+    #[error("Co: Date out of range: {0}.")]
+    DateOutOfRange(f64),
+    /// Malformed row in strict mode.
+    #[error("Co: Malformed row: {0}.")]
+    MalformedRow(LoadWarning),
     /// Sql Error.
     #[error("Co: SQL error: {0}.")]
     Sql(#[from] rusqlite::Error),
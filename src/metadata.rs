@@ -0,0 +1,169 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+use super::{CatalogVersion, Result};
+
+/// The Core Data store metadata, from the binary plist in
+/// `Z_METADATA.Z_PLIST`.
+#[derive(Debug, Default)]
+pub struct StoreMetadata {
+    /// NSStoreUUID
+    pub store_uuid: Option<String>,
+    /// NSStoreType. Should be "SQLite".
+    pub store_type: Option<String>,
+    /// NSStoreModelVersionIdentifiers
+    pub version_identifiers: Vec<String>,
+    /// NSStoreModelVersionHashes: the hash of each entity, by name.
+    pub entity_hashes: BTreeMap<String, Vec<u8>>,
+    /// NSStoreModelVersionHashesVersion
+    pub hashes_version: Option<u64>,
+    /// NSPersistenceFrameworkVersion
+    pub framework_version: Option<u64>,
+}
+
+impl StoreMetadata {
+    /// Parse the plist `data`.
+    pub fn from_plist(data: &[u8]) -> Result<StoreMetadata> {
+        let value = plist::Value::from_reader(Cursor::new(data))?;
+        let mut metadata = StoreMetadata::default();
+        let dict = match value.as_dictionary() {
+            Some(dict) => dict,
+            None => return Ok(metadata),
+        };
+
+        metadata.store_uuid = dict
+            .get("NSStoreUUID")
+            .and_then(|v| v.as_string())
+            .map(String::from);
+        metadata.store_type = dict
+            .get("NSStoreType")
+            .and_then(|v| v.as_string())
+            .map(String::from);
+        if let Some(identifiers) = dict
+            .get("NSStoreModelVersionIdentifiers")
+            .and_then(|v| v.as_array())
+        {
+            metadata.version_identifiers = identifiers
+                .iter()
+                .filter_map(|v| v.as_string())
+                .map(String::from)
+                .collect();
+        }
+        if let Some(hashes) = dict
+            .get("NSStoreModelVersionHashes")
+            .and_then(|v| v.as_dictionary())
+        {
+            metadata.entity_hashes = hashes
+                .iter()
+                .filter_map(|(name, hash)| hash.as_data().map(|hash| (name.clone(), hash.to_vec())))
+                .collect();
+        }
+        metadata.hashes_version = dict
+            .get("NSStoreModelVersionHashesVersion")
+            .and_then(|v| v.as_unsigned_integer());
+        metadata.framework_version = dict
+            .get("NSPersistenceFrameworkVersion")
+            .and_then(|v| v.as_unsigned_integer());
+
+        Ok(metadata)
+    }
+
+    /// The entity names of the model.
+    pub fn entities(&self) -> impl Iterator<Item = &str> {
+        self.entity_hashes.keys().map(|name| name.as_str())
+    }
+
+    /// The numeric versions found in the model version identifiers.
+    /// Identifiers like "1210" or "Capture One 12.1" are accepted.
+    pub fn model_versions(&self) -> Vec<i32> {
+        self.version_identifiers
+            .iter()
+            .filter_map(|identifier| {
+                let digits = identifier
+                    .chars()
+                    .filter(|c| c.is_ascii_digit() || *c == '.')
+                    .collect::<String>();
+                let mut parts = digits.split('.').filter(|part| !part.is_empty());
+                let major = parts.next()?.parse::<i32>().ok()?;
+                if major >= 100 {
+                    return Some(major);
+                }
+                let minor = parts
+                    .next()
+                    .and_then(|minor| minor.parse::<i32>().ok())
+                    .unwrap_or(0);
+                Some(major * 100 + minor * 10)
+            })
+            .collect()
+    }
+
+    /// The closest known schema version from the model identifiers.
+    pub fn closest_version(&self) -> CatalogVersion {
+        self.model_versions()
+            .into_iter()
+            .map(CatalogVersion::closest)
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CatalogVersion, StoreMetadata};
+
+    #[test]
+    fn test_parse() {
+        let mut hashes = plist::Dictionary::new();
+        hashes.insert("Image".into(), plist::Value::Data(vec![1, 2, 3]));
+        hashes.insert("Keyword".into(), plist::Value::Data(vec![4, 5, 6]));
+        let mut dict = plist::Dictionary::new();
+        dict.insert(
+            "NSStoreUUID".into(),
+            "8C0A3B5E-2C1F-4E4B-9D3A-6F2E1A0B9C8D".into(),
+        );
+        dict.insert("NSStoreType".into(), "SQLite".into());
+        dict.insert(
+            "NSStoreModelVersionIdentifiers".into(),
+            plist::Value::Array(vec!["Capture One 12.1".into()]),
+        );
+        dict.insert("NSStoreModelVersionHashes".into(), hashes.into());
+        dict.insert("NSPersistenceFrameworkVersion".into(), 866u64.into());
+        let mut data = vec![];
+        plist::to_writer_binary(&mut data, &plist::Value::Dictionary(dict)).unwrap();
+
+        let metadata = StoreMetadata::from_plist(&data).unwrap();
+        assert_eq!(
+            metadata.store_uuid.as_deref(),
+            Some("8C0A3B5E-2C1F-4E4B-9D3A-6F2E1A0B9C8D")
+        );
+        assert_eq!(metadata.store_type.as_deref(), Some("SQLite"));
+        assert_eq!(metadata.framework_version, Some(866));
+        assert_eq!(
+            metadata.entities().collect::<Vec<&str>>(),
+            vec!["Image", "Keyword"]
+        );
+        assert_eq!(metadata.entity_hashes["Keyword"], vec![4, 5, 6]);
+        assert_eq!(metadata.model_versions(), vec![1210]);
+        assert_eq!(metadata.closest_version(), CatalogVersion::Co1210);
+    }
+
+    #[test]
+    fn test_closest_version() {
+        let metadata = StoreMetadata {
+            version_identifiers: vec!["2450".into()],
+            ..Default::default()
+        };
+        assert_eq!(metadata.closest_version(), CatalogVersion::Co2300);
+
+        let metadata = StoreMetadata::default();
+        assert_eq!(metadata.closest_version(), CatalogVersion::Unknown);
+
+        assert!(StoreMetadata::from_plist(b"garbage").is_err());
+    }
+}
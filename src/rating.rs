@@ -0,0 +1,126 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::fmt;
+
+/// The color tag of a variant (ZCOLORTAG).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorTag {
+    #[default]
+    None,
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Pink,
+    Purple,
+}
+
+impl From<i64> for ColorTag {
+    fn from(val: i64) -> Self {
+        match val {
+            1 => ColorTag::Red,
+            2 => ColorTag::Orange,
+            3 => ColorTag::Yellow,
+            4 => ColorTag::Green,
+            5 => ColorTag::Blue,
+            6 => ColorTag::Pink,
+            7 => ColorTag::Purple,
+            _ => ColorTag::None,
+        }
+    }
+}
+
+impl From<ColorTag> for i64 {
+    fn from(tag: ColorTag) -> i64 {
+        tag as i64
+    }
+}
+
+impl ColorTag {
+    /// The `xmp:Label` value, as written by Capture One.
+    pub fn xmp_label(&self) -> Option<&'static str> {
+        match *self {
+            ColorTag::None => None,
+            ColorTag::Red => Some("Red"),
+            ColorTag::Orange => Some("Orange"),
+            ColorTag::Yellow => Some("Yellow"),
+            ColorTag::Green => Some("Green"),
+            ColorTag::Blue => Some("Blue"),
+            ColorTag::Pink => Some("Pink"),
+            ColorTag::Purple => Some("Purple"),
+        }
+    }
+
+    /// The darktable color label: 0 red, 1 yellow, 2 green, 3 blue
+    /// and 4 purple. Orange and pink have no equivalent.
+    pub fn darktable_label(&self) -> Option<u8> {
+        match *self {
+            ColorTag::Red => Some(0),
+            ColorTag::Yellow => Some(1),
+            ColorTag::Green => Some(2),
+            ColorTag::Blue => Some(3),
+            ColorTag::Purple => Some(4),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ColorTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.xmp_label().unwrap_or(""))
+    }
+}
+
+/// The pick state of a variant (ZPICKSTATE).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PickState {
+    #[default]
+    Unflagged,
+    Picked,
+    Rejected,
+}
+
+impl From<i64> for PickState {
+    fn from(val: i64) -> Self {
+        match val {
+            1 => PickState::Picked,
+            -1 => PickState::Rejected,
+            _ => PickState::Unflagged,
+        }
+    }
+}
+
+/// The `xmp:Rating` for `rating`: -1 for rejected, otherwise 0 to 5.
+pub fn xmp_rating(rating: i32, pick: PickState) -> i32 {
+    if pick == PickState::Rejected {
+        -1
+    } else {
+        rating.clamp(0, 5)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{xmp_rating, ColorTag, PickState};
+
+    #[test]
+    fn test_rating() {
+        assert_eq!(ColorTag::from(4), ColorTag::Green);
+        assert_eq!(ColorTag::from(42), ColorTag::None);
+        assert_eq!(i64::from(ColorTag::Purple), 7);
+        assert_eq!(ColorTag::Green.xmp_label(), Some("Green"));
+        assert_eq!(ColorTag::None.xmp_label(), None);
+        assert_eq!(ColorTag::Purple.darktable_label(), Some(4));
+        assert_eq!(ColorTag::Orange.darktable_label(), None);
+
+        assert_eq!(PickState::from(-1), PickState::Rejected);
+        assert_eq!(xmp_rating(4, PickState::Rejected), -1);
+        assert_eq!(xmp_rating(4, PickState::Picked), 4);
+        assert_eq!(xmp_rating(7, PickState::Unflagged), 5);
+    }
+}
@@ -0,0 +1,97 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::fmt;
+
+use super::{CoId, Error, Result};
+
+/// How the loaders handle malformed rows.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LoadMode {
+    /// Skip the row and report a warning.
+    #[default]
+    Lenient,
+    /// Fail with `Error::MalformedRow`.
+    Strict,
+}
+
+/// A row that couldn't be loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoadWarning {
+    pub table: String,
+    /// Z_PK of the row. For a join table, the id of the owner.
+    pub id: CoId,
+    /// The offending column, if known.
+    pub column: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.table, self.id)?;
+        if let Some(ref column) = self.column {
+            write!(f, " {}", column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Collect the warnings while loading.
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    mode: LoadMode,
+    warnings: Vec<LoadWarning>,
+}
+
+impl LoadReport {
+    pub fn new(mode: LoadMode) -> LoadReport {
+        LoadReport {
+            mode,
+            warnings: vec![],
+        }
+    }
+
+    pub fn mode(&self) -> LoadMode {
+        self.mode
+    }
+
+    pub fn warnings(&self) -> &[LoadWarning] {
+        &self.warnings
+    }
+
+    /// Report that the row `id` from `table` failed to load with
+    /// `err`. Return an error in strict mode.
+    pub fn skip(
+        &mut self,
+        table: &str,
+        id: CoId,
+        row: &rusqlite::Row,
+        err: rusqlite::Error,
+    ) -> Result<()> {
+        let column = match err {
+            rusqlite::Error::InvalidColumnType(_, ref name, _) => Some(name.clone()),
+            rusqlite::Error::FromSqlConversionFailure(idx, _, _)
+            | rusqlite::Error::IntegralValueOutOfRange(idx, _)
+            | rusqlite::Error::InvalidColumnIndex(idx) => {
+                row.as_ref().column_name(idx).ok().map(String::from)
+            }
+            _ => None,
+        };
+        let warning = LoadWarning {
+            table: table.to_string(),
+            id,
+            column,
+            message: err.to_string(),
+        };
+        match self.mode {
+            LoadMode::Strict => Err(Error::MalformedRow(warning)),
+            LoadMode::Lenient => {
+                self.warnings.push(warning);
+                Ok(())
+            }
+        }
+    }
+}
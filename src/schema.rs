@@ -0,0 +1,531 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! The database schema.
+//!
+//! Each loader asks the `Schema` for the SQL of its `Query`. The
+//! columns are selected in the order of the `Mapping`, so the loader
+//! can get the values by index. All the known versions share the
+//! same mappings, with optional columns for the differences.
+
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::Connection;
+
+use super::{Error, Result};
+
+/// The queries the loaders need.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum Query {
+    /// Entities.
+    Entities,
+    /// Version history.
+    VersionInfo,
+    /// Document content.
+    DocumentContent,
+    /// Keywords. Bound to the entity.
+    Keywords,
+    /// Path locations. Bound to the entity.
+    Folders,
+    /// Images. Bound to the entity.
+    Images,
+    /// Stacks. Bound to the entity.
+    Stacks,
+    /// Images of all the stacks: link id, stack id, image id. Ordered
+    /// by index.
+    StackContent,
+    /// Collections, all entities.
+    Collections,
+    /// Stacks of all the collections: collection id, stack id.
+    CollectionContent,
+    /// Variants. Bound to the entity.
+    Variants,
+    /// Variant metadata, then the columns not in the mapping.
+    VariantMetadata,
+}
+
+/// A column in a `Mapping`.
+#[derive(Debug)]
+pub(crate) enum Column {
+    /// The column must exist.
+    Required(&'static str),
+    /// The column might not exist. `NULL` is selected instead.
+    Optional(&'static str),
+}
+
+/// How a `Query` maps to the database.
+#[derive(Debug)]
+pub(crate) struct Mapping {
+    pub table: &'static str,
+    /// The columns, in the order the loader expect them.
+    pub columns: &'static [Column],
+    /// The `WHERE` clause, with the parameter.
+    pub filter: Option<&'static str>,
+    /// The `ORDER BY` clause.
+    pub order: Option<&'static str>,
+}
+
+const ENTITIES: Mapping = Mapping {
+    table: "ZENTITIES",
+    columns: &[Column::Required("Z_ENT"), Column::Required("ZNAME")],
+    filter: None,
+    order: None,
+};
+
+const VERSION_INFO: Mapping = Mapping {
+    table: "ZVERSIONINFO",
+    columns: &[
+        Column::Required("Z_PK"),
+        Column::Required("ZVERSION"),
+        Column::Optional("ZCOMPATIBLEVERSION"),
+        Column::Optional("ZAUTHOR"),
+        Column::Optional("ZFORMAT"),
+        Column::Optional("ZCOMPATIBILITY"),
+    ],
+    filter: None,
+    order: Some("Z_PK"),
+};
+
+const DOCUMENT_CONTENT: Mapping = Mapping {
+    table: "ZDOCUMENTCONTENT",
+    columns: &[Column::Required("ZROOTCOLLECTION")],
+    filter: None,
+    order: None,
+};
+
+const KEYWORDS: Mapping = Mapping {
+    table: "ZKEYWORD",
+    columns: &[
+        Column::Required("Z_PK"),
+        Column::Required("ZNAME"),
+        Column::Required("ZPARENT"),
+    ],
+    filter: Some("Z_ENT=?1"),
+    order: None,
+};
+
+const FOLDERS: Mapping = Mapping {
+    table: "ZPATHLOCATION",
+    columns: &[
+        Column::Required("Z_PK"),
+        Column::Required("ZMACROOT"),
+        Column::Required("ZRELATIVEPATH"),
+        Column::Required("ZISRELATIVE"),
+    ],
+    filter: Some("Z_ENT=?1"),
+    order: None,
+};
+
+const IMAGES: Mapping = Mapping {
+    table: "ZIMAGE",
+    columns: &[
+        Column::Required("Z_PK"),
+        Column::Required("ZIMAGEUUID"),
+        Column::Required("ZIMAGELOCATION"),
+        Column::Required("ZDISPLAYNAME"),
+        Column::Required("ZIMAGEFILENAME"),
+        Column::Required("ZIMAGECLASSIFICATION"),
+        Column::Required("ZEXP_FORMAT"),
+        Column::Optional("ZGPSALTITUDE"),
+        Column::Optional("ZGPSLATITUDE"),
+        Column::Optional("ZGPSLONGITUDE"),
+        Column::Optional("ZEXP_CAPTUREDATE"),
+        Column::Optional("ZEXP_CAPTURETIMEZONE"),
+        Column::Optional("ZEXP_CAMERAMAKE"),
+        Column::Optional("ZEXP_CAMERAMODEL"),
+        Column::Optional("ZEXP_LENS"),
+        Column::Optional("ZEXP_FOCALLENGTH"),
+        Column::Optional("ZEXP_APERTURE"),
+        Column::Optional("ZEXP_SHUTTERSPEED"),
+        Column::Optional("ZEXP_ISO"),
+        Column::Optional("ZEXP_EXPOSURECOMPENSATION"),
+        Column::Optional("ZEXP_ORIENTATION"),
+        Column::Optional("ZWIDTH"),
+        Column::Optional("ZHEIGHT"),
+        Column::Optional("ZFILE_SIZE"),
+    ],
+    filter: Some("Z_ENT=?1"),
+    order: None,
+};
+
+const STACKS: Mapping = Mapping {
+    table: "ZSTACK",
+    columns: &[
+        Column::Required("Z_PK"),
+        Column::Required("ZCOLLECTION"),
+        Column::Required("ZPICKEDIMAGE"),
+    ],
+    filter: Some("Z_ENT=?1"),
+    order: None,
+};
+
+const STACK_CONTENT: Mapping = Mapping {
+    table: "ZSTACKIMAGELINK",
+    columns: &[
+        Column::Required("Z_PK"),
+        Column::Required("ZSTACK"),
+        Column::Required("ZIMAGE"),
+        // For the order.
+        Column::Required("ZINDEX"),
+    ],
+    filter: None,
+    order: Some("ZINDEX, Z_PK"),
+};
+
+const COLLECTIONS: Mapping = Mapping {
+    table: "ZCOLLECTION",
+    columns: &[
+        Column::Required("Z_ENT"),
+        Column::Required("Z_PK"),
+        Column::Required("ZNAME"),
+        Column::Required("ZPARENT"),
+        Column::Required("ZFOLDERLOCATION"),
+        Column::Optional("ZCOLLECTIONINDX"),
+        Column::Optional("ZTRASHCOLLECTION"),
+        Column::Optional("ZDATECREATED"),
+        Column::Optional("ZDATEMODIFIED"),
+        Column::Optional("ZSORTORDER"),
+        Column::Optional("ZPRIMARYVARIANT"),
+    ],
+    filter: None,
+    order: None,
+};
+
+const COLLECTION_CONTENT: Mapping = Mapping {
+    table: "ZSTACK",
+    columns: &[Column::Required("ZCOLLECTION"), Column::Required("Z_PK")],
+    filter: None,
+    order: None,
+};
+
+const VARIANTS: Mapping = Mapping {
+    table: "ZVARIANT",
+    columns: &[
+        Column::Required("Z_PK"),
+        Column::Required("ZVARIANTUUID"),
+        Column::Required("ZIMAGE"),
+        Column::Optional("ZPROCESSINGENGINE"),
+        Column::Optional("ZPRODUCTID"),
+        Column::Optional("ZDEFAULTLAYER"),
+        Column::Optional("ZADJUSTMENTLAYER"),
+        Column::Optional("ZCOMBINEDSETTINGS"),
+        Column::Optional("ZRATING"),
+        Column::Optional("ZCOLORTAG"),
+        Column::Optional("ZPICKSTATE"),
+    ],
+    filter: Some("Z_ENT=?1"),
+    // The primary variant is the first.
+    order: Some("Z_PK"),
+};
+
+const VARIANT_METADATA: Mapping = Mapping {
+    table: "ZVARIANTMETADATA",
+    columns: &[
+        Column::Required("Z_PK"),
+        Column::Required("ZVARIANT"),
+        Column::Optional("ZTITLE"),
+        Column::Optional("ZHEADLINE"),
+        Column::Optional("ZCAPTION"),
+        Column::Optional("ZCAPTIONWRITER"),
+        Column::Optional("ZCREATOR"),
+        Column::Optional("ZCREATORJOBTITLE"),
+        Column::Optional("ZCREATORADDRESS"),
+        Column::Optional("ZCREATORCITY"),
+        Column::Optional("ZCREATORSTATE"),
+        Column::Optional("ZCREATORPOSTALCODE"),
+        Column::Optional("ZCREATORCOUNTRY"),
+        Column::Optional("ZCREATORPHONE"),
+        Column::Optional("ZCREATOREMAIL"),
+        Column::Optional("ZCREATORURL"),
+        Column::Optional("ZCOPYRIGHT"),
+        Column::Optional("ZUSAGETERMS"),
+        Column::Optional("ZCREDIT"),
+        Column::Optional("ZSOURCE"),
+        Column::Optional("ZINSTRUCTIONS"),
+        Column::Optional("ZJOBIDENTIFIER"),
+        Column::Optional("ZINTELLECTUALGENRE"),
+        Column::Optional("ZSCENE"),
+        Column::Optional("ZSUBJECTCODE"),
+        Column::Optional("ZIPTCDATECREATED"),
+        Column::Optional("ZSUBLOCATION"),
+        Column::Optional("ZCITY"),
+        Column::Optional("ZPROVINCESTATE"),
+        Column::Optional("ZCOUNTRY"),
+        Column::Optional("ZCOUNTRYCODE"),
+        Column::Optional("ZPERSONINIMAGE"),
+        Column::Optional("ZEVENT"),
+        Column::Optional("ZORGANISATIONNAME"),
+        Column::Optional("ZORGANISATIONCODE"),
+    ],
+    filter: None,
+    order: None,
+};
+
+/// Index of the capture info columns in the IMAGES rows.
+pub(crate) const IMAGES_CAPTURE_INFO: usize = column_index(&IMAGES, "ZEXP_CAPTUREDATE");
+
+/// Index of the column `name` in the rows of `mapping`. Evaluated at
+/// compile time, so a missing column doesn't build.
+const fn column_index(mapping: &Mapping, name: &str) -> usize {
+    let mut idx = 0;
+    while idx < mapping.columns.len() {
+        let (Column::Required(column) | Column::Optional(column)) = mapping.columns[idx];
+        if column.len() == name.len() {
+            let (column, name) = (column.as_bytes(), name.as_bytes());
+            let mut i = 0;
+            while i < name.len() && column[i] == name[i] {
+                i += 1;
+            }
+            if i == name.len() {
+                return idx;
+            }
+        }
+        idx += 1;
+    }
+    panic!("column not in the mapping");
+}
+
+/// Get the mapping of `query`.
+fn mapping(query: Query) -> &'static Mapping {
+    match query {
+        Query::Entities => &ENTITIES,
+        Query::VersionInfo => &VERSION_INFO,
+        Query::DocumentContent => &DOCUMENT_CONTENT,
+        Query::Keywords => &KEYWORDS,
+        Query::Folders => &FOLDERS,
+        Query::Images => &IMAGES,
+        Query::Stacks => &STACKS,
+        Query::StackContent => &STACK_CONTENT,
+        Query::Collections => &COLLECTIONS,
+        Query::CollectionContent => &COLLECTION_CONTENT,
+        Query::Variants => &VARIANTS,
+        Query::VariantMetadata => &VARIANT_METADATA,
+    }
+}
+
+/// Get the set of column names for `table`.
+pub(crate) fn table_columns(conn: &Connection, table: &str) -> Result<HashSet<String>> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let columns = stmt
+        .query_map([table], |row| row.get(0))?
+        .collect::<rusqlite::Result<HashSet<String>>>()?;
+    Ok(columns)
+}
+
+/// The columns appended by `Schema::query_with_extra()`.
+#[derive(Debug)]
+pub(crate) struct ExtraColumns {
+    /// Index of the first extra column in the row.
+    pub first: usize,
+    /// The names of the extra columns, in order.
+    pub names: Vec<String>,
+}
+
+/// The schema of an open catalog.
+#[derive(Debug, Default)]
+pub(crate) struct Schema {
+    /// The columns of each table in the database.
+    columns: HashMap<String, HashSet<String>>,
+}
+
+impl Schema {
+    /// Detect the schema from `conn`.
+    pub fn new(conn: &Connection) -> Result<Schema> {
+        let mut columns: HashMap<String, HashSet<String>> = HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT m.name, p.name FROM sqlite_master m, pragma_table_info(m.name) p \
+             WHERE m.type = 'table'",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            columns.entry(row.get(0)?).or_default().insert(row.get(1)?);
+        }
+
+        Ok(Schema { columns })
+    }
+
+    /// Whether `column` exists in `table`.
+    pub fn has_column(&self, table: &str, column: &str) -> bool {
+        self.columns
+            .get(table)
+            .map(|columns| columns.contains(column))
+            .unwrap_or(false)
+    }
+
+    /// Build the SQL for `query`.
+    ///
+    /// Return `Error::MissingTable` or `Error::MissingColumn` if the
+    /// database doesn't match the mapping.
+    pub fn query(&self, query: Query) -> Result<String> {
+        let mapping = mapping(query);
+        let mut sql = self.select(mapping, &[])?;
+        Self::push_clauses(&mut sql, mapping);
+        Ok(sql)
+    }
+
+    /// Build the SQL for `query`, with the columns of the table not in
+    /// the mapping appended. Return the SQL and these extra columns.
+    /// The Core Data columns `Z_ENT`, `Z_OPT` and `Z_PK` are never
+    /// extra.
+    pub fn query_with_extra(&self, query: Query) -> Result<(String, ExtraColumns)> {
+        let mapping = mapping(query);
+        let mut extra = self
+            .columns
+            .get(mapping.table)
+            .into_iter()
+            .flatten()
+            .filter(|name| !matches!(name.as_str(), "Z_ENT" | "Z_OPT" | "Z_PK"))
+            .filter(|name| {
+                !mapping.columns.iter().any(|column| match column {
+                    Column::Required(c) | Column::Optional(c) => c == name,
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        extra.sort_unstable();
+        let mut sql = self.select(mapping, &extra)?;
+        Self::push_clauses(&mut sql, mapping);
+        Ok((
+            sql,
+            ExtraColumns {
+                first: mapping.columns.len(),
+                names: extra,
+            },
+        ))
+    }
+
+    /// Build the SQL for a page of `query`, ordered by `Z_PK`. The
+    /// parameters are the one of the filter, then the last `Z_PK`
+    /// of the previous page and the page size.
+    pub fn paged_query(&self, query: Query) -> Result<String> {
+        let mapping = mapping(query);
+        let mut sql = self.select(mapping, &[])?;
+        if let Some(filter) = mapping.filter {
+            sql.push_str(" WHERE ");
+            sql.push_str(filter);
+            sql.push_str(" AND Z_PK > ?2 ORDER BY Z_PK LIMIT ?3");
+        } else {
+            sql.push_str(" WHERE Z_PK > ?1 ORDER BY Z_PK LIMIT ?2");
+        }
+        Ok(sql)
+    }
+
+    /// Append the `WHERE` and `ORDER BY` clauses of `mapping`.
+    fn push_clauses(sql: &mut String, mapping: &Mapping) {
+        if let Some(filter) = mapping.filter {
+            sql.push_str(" WHERE ");
+            sql.push_str(filter);
+        }
+        if let Some(order) = mapping.order {
+            sql.push_str(" ORDER BY ");
+            sql.push_str(order);
+        }
+    }
+
+    /// Build the `SELECT` part for `mapping`, then the `extra` columns.
+    fn select(&self, mapping: &Mapping, extra: &[String]) -> Result<String> {
+        let table_columns = self
+            .columns
+            .get(mapping.table)
+            .filter(|columns| !columns.is_empty())
+            .ok_or_else(|| Error::MissingTable(mapping.table.to_string()))?;
+        let mut columns = mapping
+            .columns
+            .iter()
+            .map(|column| match column {
+                Column::Required(name) => {
+                    if table_columns.contains(*name) {
+                        Ok(name.to_string())
+                    } else {
+                        Err(Error::MissingColumn(
+                            mapping.table.to_string(),
+                            name.to_string(),
+                        ))
+                    }
+                }
+                Column::Optional(name) => Ok(if self.has_column(mapping.table, name) {
+                    name.to_string()
+                } else {
+                    "NULL".to_string()
+                }),
+            })
+            .collect::<Result<Vec<String>>>()?;
+        columns.extend(extra.iter().map(|name| format!("\"{}\"", name)));
+        Ok(format!(
+            "SELECT {} FROM {}",
+            columns.join(", "),
+            mapping.table
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use rusqlite::Connection;
+
+    use super::{Query, Schema};
+    use crate::Error;
+
+    #[test]
+    fn test_query() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE ZIMAGE (Z_PK INTEGER, Z_ENT INTEGER, ZIMAGEUUID VARCHAR, \
+             ZIMAGELOCATION INTEGER, ZDISPLAYNAME VARCHAR, ZIMAGEFILENAME VARCHAR, \
+             ZIMAGECLASSIFICATION INTEGER, ZEXP_FORMAT VARCHAR, ZGPSLATITUDE FLOAT);
+             CREATE TABLE ZCOLLECTION (Z_PK INTEGER, Z_ENT INTEGER, ZNAME VARCHAR);
+             CREATE TABLE ZVARIANTMETADATA (Z_PK INTEGER, Z_ENT INTEGER, Z_OPT INTEGER, \
+             ZVARIANT INTEGER, ZTITLE VARCHAR, ZZZ VARCHAR, ZAAA INTEGER);
+             CREATE TABLE ZSTACKIMAGELINK (Z_PK INTEGER, ZSTACK INTEGER, ZIMAGE INTEGER);",
+        )
+        .unwrap();
+        let schema = Schema::new(&conn).unwrap();
+        assert!(schema.has_column("ZIMAGE", "ZGPSLATITUDE"));
+        assert!(!schema.has_column("ZIMAGE", "ZGPSALTITUDE"));
+        assert!(!schema.has_column("ZKEYWORD", "ZNAME"));
+
+        // The GPS longitude and the capture info are missing.
+        let missing = ["NULL"; 15].join(", ");
+        assert_eq!(
+            schema.query(Query::Images).unwrap(),
+            format!(
+                "SELECT Z_PK, ZIMAGEUUID, ZIMAGELOCATION, ZDISPLAYNAME, ZIMAGEFILENAME, \
+                 ZIMAGECLASSIFICATION, ZEXP_FORMAT, NULL, ZGPSLATITUDE, {missing} FROM ZIMAGE \
+                 WHERE Z_ENT=?1"
+            )
+        );
+        assert_eq!(
+            schema.paged_query(Query::Images).unwrap(),
+            format!(
+                "SELECT Z_PK, ZIMAGEUUID, ZIMAGELOCATION, ZDISPLAYNAME, ZIMAGEFILENAME, \
+                 ZIMAGECLASSIFICATION, ZEXP_FORMAT, NULL, ZGPSLATITUDE, {missing} FROM ZIMAGE \
+                 WHERE Z_ENT=?1 AND Z_PK > ?2 ORDER BY Z_PK LIMIT ?3"
+            )
+        );
+        let (sql, extra) = schema.query_with_extra(Query::VariantMetadata).unwrap();
+        assert_eq!(extra.names, vec!["ZAAA", "ZZZ"]);
+        // The extra columns are after the mapped ones.
+        let columns = sql
+            .trim_start_matches("SELECT ")
+            .split(", ")
+            .collect::<Vec<_>>();
+        assert_eq!(columns[extra.first], "\"ZAAA\"");
+        assert!(sql.starts_with("SELECT Z_PK, ZVARIANT, ZTITLE, NULL"));
+        assert!(sql.ends_with(", \"ZAAA\", \"ZZZ\" FROM ZVARIANTMETADATA"));
+        assert!(matches!(
+            schema.query(Query::Keywords),
+            Err(Error::MissingTable(table)) if table == "ZKEYWORD"
+        ));
+        assert!(matches!(
+            schema.query(Query::Collections),
+            Err(Error::MissingColumn(table, column)) if table == "ZCOLLECTION" && column == "ZPARENT"
+        ));
+        assert!(matches!(
+            schema.query(Query::StackContent),
+            Err(Error::MissingColumn(table, column)) if table == "ZSTACKIMAGELINK" && column == "ZINDEX"
+        ));
+    }
+}
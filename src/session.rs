@@ -0,0 +1,224 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+use super::{Catalog, Error, Folder, Result};
+
+/// The extension of the session database file.
+const SESSION_DB_EXTENSION: &str = "cosessiondb";
+
+/// The standard folders of a session.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SessionFolder {
+    Capture,
+    Selects,
+    Output,
+    Trash,
+}
+
+impl SessionFolder {
+    /// The folder name, relative to the session.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            SessionFolder::Capture => "Capture",
+            SessionFolder::Selects => "Selects",
+            SessionFolder::Output => "Output",
+            SessionFolder::Trash => "Trash",
+        }
+    }
+}
+
+/// A Capture One session. A folder with a `.cosessiondb` database
+/// with the same schema as a catalog, and the image folders.
+///
+/// Dereference to the `Catalog` to load the content.
+pub struct Session {
+    /// Session folder
+    path: PathBuf,
+    catalog: Catalog,
+}
+
+impl Session {
+    pub fn new(path: &Path) -> Self {
+        Session {
+            path: PathBuf::from(path),
+            catalog: Catalog::new(path),
+        }
+    }
+
+    /// Whether `path` is a session folder or a session database.
+    pub fn is_session(path: &Path) -> bool {
+        matches!(Self::find_db(path), Ok(_) | Err(Error::AmbiguousSession))
+    }
+
+    /// Locate the session database. `path` is either the session
+    /// folder or the database itself. In the folder, the database
+    /// named after the session is preferred, otherwise it must be the
+    /// only one.
+    fn find_db(path: &Path) -> Result<PathBuf> {
+        if path.is_file() {
+            return path
+                .extension()
+                .filter(|ext| *ext == SESSION_DB_EXTENSION)
+                .map(|_| PathBuf::from(path))
+                .ok_or(Error::NoDatabase);
+        }
+        // Look for the name of the session first.
+        if let Some(name) = path.file_name() {
+            let mut file_name = name.to_os_string();
+            file_name.push(".");
+            file_name.push(SESSION_DB_EXTENSION);
+            let db = path.join(file_name);
+            if db.is_file() {
+                return Ok(db);
+            }
+        }
+        let mut candidates = std::fs::read_dir(path)
+            .map_err(|_| Error::NoDatabase)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|entry| {
+                entry.is_file()
+                    && entry
+                        .extension()
+                        .map(|ext| ext == SESSION_DB_EXTENSION)
+                        .unwrap_or(false)
+            });
+        match (candidates.next(), candidates.next()) {
+            (Some(db), None) => Ok(db),
+            (Some(_), Some(_)) => Err(Error::AmbiguousSession),
+            (None, _) => Err(Error::NoDatabase),
+        }
+    }
+
+    pub fn open(&mut self) -> Result<()> {
+        let db = Self::find_db(&self.path)?;
+        if self.path.is_file() {
+            if let Some(parent) = db.parent() {
+                self.path = PathBuf::from(parent);
+            }
+        }
+        self.catalog = Catalog::new(&db);
+        self.catalog.open()
+    }
+
+    /// The session folder.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the standard `folder` of the session.
+    pub fn folder(&self, folder: SessionFolder) -> PathBuf {
+        self.path.join(folder.name())
+    }
+
+    /// Consume the session to return the catalog.
+    pub fn into_catalog(self) -> Catalog {
+        self.catalog
+    }
+
+    /// Resolve the path of `folder`. Relative folders are relative to
+    /// the session.
+    pub fn resolve_folder(&self, folder: &Folder) -> PathBuf {
+        if folder.is_relative {
+            self.path.join(&folder.path_from_root)
+        } else {
+            Path::new(&folder.root_folder).join(&folder.path_from_root)
+        }
+    }
+}
+
+impl Deref for Session {
+    type Target = Catalog;
+
+    fn deref(&self) -> &Catalog {
+        &self.catalog
+    }
+}
+
+impl DerefMut for Session {
+    fn deref_mut(&mut self) -> &mut Catalog {
+        &mut self.catalog
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{Session, SessionFolder};
+    use crate::fixtures::Fixture;
+    use crate::{CollectionType, Error};
+
+    #[test]
+    fn test_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_dir = dir.path().join("Wedding");
+        std::fs::create_dir(&session_dir).unwrap();
+        Fixture::new(1300).create(&session_dir.join("Wedding.cosessiondb"));
+
+        assert!(Session::is_session(&session_dir));
+        assert!(!Session::is_session(dir.path()));
+
+        let mut session = Session::new(&session_dir);
+        session.open().unwrap();
+        assert_eq!(
+            session.folder(SessionFolder::Capture),
+            session_dir.join("Capture")
+        );
+        assert_eq!(
+            session.folder(SessionFolder::Trash),
+            session_dir.join("Trash")
+        );
+
+        assert_eq!(session.load_keywords().unwrap().len(), 3);
+        assert_eq!(session.load_images().unwrap().len(), 3);
+        assert!(session
+            .load_collections()
+            .unwrap()
+            .iter()
+            .any(|collection| matches!(collection.collection_type, CollectionType::Album(_))));
+
+        let folders = session.load_folders().unwrap().clone();
+        assert_eq!(folders.len(), 2);
+        let relative = session.resolve_folder(&folders[1]);
+        assert_eq!(relative, session_dir.join("Originals/"));
+        let absolute = session.resolve_folder(&folders[0]);
+        assert_eq!(absolute, PathBuf::from("/Volumes/Photos/2019/"));
+
+        // Open from the database file.
+        let mut session = Session::new(&session_dir.join("Wedding.cosessiondb"));
+        session.open().unwrap();
+        assert_eq!(session.path(), session_dir);
+    }
+
+    #[test]
+    fn test_find_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_dir = dir.path().join("Wedding 2023.05.01");
+        std::fs::create_dir(&session_dir).unwrap();
+        Fixture::new(1300).create(&session_dir.join("Backup.cosessiondb"));
+        assert_eq!(
+            Session::find_db(&session_dir).unwrap(),
+            session_dir.join("Backup.cosessiondb")
+        );
+
+        Fixture::new(1300).create(&session_dir.join("Other.cosessiondb"));
+        assert!(matches!(
+            Session::find_db(&session_dir),
+            Err(Error::AmbiguousSession)
+        ));
+        assert!(Session::is_session(&session_dir));
+
+        Fixture::new(1300).create(&session_dir.join("Wedding 2023.05.01.cosessiondb"));
+        assert_eq!(
+            Session::find_db(&session_dir).unwrap(),
+            session_dir.join("Wedding 2023.05.01.cosessiondb")
+        );
+    }
+}
@@ -4,7 +4,10 @@
  file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use super::CoId;
+use std::collections::HashMap;
+
+use super::schema::{Query, Schema};
+use super::{CoId, LoadReport, Result};
 
 #[derive(Default)]
 pub struct Stack {
@@ -16,33 +19,57 @@ pub struct Stack {
 }
 
 impl Stack {
-    pub fn load_objects(conn: &rusqlite::Connection, entity: CoId) -> Vec<Stack> {
+    pub(crate) fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Stack> {
+        Ok(Stack {
+            id: row.get(0)?,
+            collection: row.get(1)?,
+            pick: row.get(2)?,
+            content: None,
+        })
+    }
+
+    pub(crate) fn load_objects(
+        conn: &rusqlite::Connection,
+        schema: &Schema,
+        entity: CoId,
+        report: &mut LoadReport,
+    ) -> Result<Vec<Stack>> {
         let mut stacks: Vec<Stack> = vec![];
-        if let Ok(mut stmt) =
-            conn.prepare("SELECT Z_PK, ZCOLLECTION, ZPICKEDIMAGE FROM ZSTACK WHERE Z_ENT=?1")
-        {
-            let mut rows = stmt.query([&entity]).unwrap();
-            while let Ok(Some(row)) = rows.next() {
-                stacks.push(Stack {
-                    id: row.get(0).unwrap(),
-                    collection: row.get(1).unwrap(),
-                    pick: row.get(2).unwrap(),
-                    content: None,
-                });
+        let mut stmt = conn.prepare(&schema.query(Query::Stacks)?)?;
+        let mut rows = stmt.query([&entity])?;
+        while let Some(row) = rows.next()? {
+            match Stack::from_row(row) {
+                Ok(stack) => stacks.push(stack),
+                Err(err) => report.skip("ZSTACK", row.get(0)?, row, err)?,
             }
         }
 
-        stacks
+        Ok(stacks)
     }
 
-    pub fn get_content(&mut self, conn: &rusqlite::Connection) {
-        let mut ids: Vec<CoId> = vec![];
-        if let Ok(mut stmt) = conn.prepare("SELECT ZIMAGE FROM ZSTACKIMAGELINK WHERE ZSTACK=?1") {
-            let mut rows = stmt.query([&self.id]).unwrap();
-            while let Ok(Some(row)) = rows.next() {
-                ids.push(row.get(0).unwrap());
+    /// Load the content of all the `stacks` in one query.
+    pub(crate) fn load_content(
+        conn: &rusqlite::Connection,
+        schema: &Schema,
+        stacks: &mut [Stack],
+        report: &mut LoadReport,
+    ) -> Result<()> {
+        let mut content: HashMap<CoId, Vec<CoId>> = HashMap::new();
+        let mut stmt = conn.prepare(&schema.query(Query::StackContent)?)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            // Links not in a stack are skipped.
+            match (row.get::<usize, Option<CoId>>(1), row.get(2)) {
+                (Ok(Some(stack)), Ok(image)) => content.entry(stack).or_default().push(image),
+                (Ok(None), Ok(_)) => {}
+                (Err(err), _) | (_, Err(err)) => {
+                    report.skip("ZSTACKIMAGELINK", row.get(0)?, row, err)?
+                }
             }
-            self.content = Some(ids);
         }
+        for stack in stacks.iter_mut() {
+            stack.content = Some(content.remove(&stack.id).unwrap_or_default());
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,79 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use super::schema::{Query, Schema};
+use super::{xmp_rating, CoId, ColorTag, LoadReport, PickState, Result};
+
+/// An edited version of an `Image`. Each image has at least one,
+/// the others are clones or virtual copies.
+#[derive(Default)]
+pub struct Variant {
+    pub id: CoId,
+    pub uuid: String,
+    /// The image this is a variant of.
+    pub image: CoId,
+    /// Version of the processing engine, like 1200 for Capture One 12.
+    pub processing_engine: Option<i32>,
+    /// The type of file, like "FujiXT3" or "FileSystemJpeg".
+    pub product_id: Option<String>,
+    /// ZVARIANTLAYER ids.
+    pub default_layer: Option<CoId>,
+    pub adjustment_layer: Option<CoId>,
+    pub combined_settings: Option<CoId>,
+    /// Star rating, 0 to 5. `None` if not set (ZRATING).
+    pub rating: Option<i32>,
+    /// `None` if not set (ZCOLORTAG).
+    pub color_tag: Option<ColorTag>,
+    /// `None` if not set (ZPICKSTATE).
+    pub pick: Option<PickState>,
+}
+
+impl Variant {
+    pub(crate) fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Variant> {
+        Ok(Variant {
+            id: row.get(0)?,
+            uuid: row.get(1)?,
+            image: row.get(2)?,
+            processing_engine: row.get(3)?,
+            product_id: row.get(4)?,
+            default_layer: row.get(5)?,
+            adjustment_layer: row.get(6)?,
+            combined_settings: row.get(7)?,
+            rating: row.get(8)?,
+            color_tag: row.get::<usize, Option<i64>>(9)?.map(ColorTag::from),
+            pick: row.get::<usize, Option<i64>>(10)?.map(PickState::from),
+        })
+    }
+
+    /// The `xmp:Rating`, -1 if rejected. `None` if neither the
+    /// rating nor the pick state are set.
+    pub fn xmp_rating(&self) -> Option<i32> {
+        match (self.rating, self.pick) {
+            (None, Some(PickState::Rejected)) => Some(-1),
+            (None, _) => None,
+            (Some(rating), pick) => Some(xmp_rating(rating, pick.unwrap_or_default())),
+        }
+    }
+
+    pub(crate) fn load_objects(
+        conn: &rusqlite::Connection,
+        schema: &Schema,
+        entity: CoId,
+        report: &mut LoadReport,
+    ) -> Result<Vec<Variant>> {
+        let mut variants: Vec<Variant> = vec![];
+        let mut stmt = conn.prepare(&schema.query(Query::Variants)?)?;
+        let mut rows = stmt.query([&entity])?;
+        while let Some(row) = rows.next()? {
+            match Variant::from_row(row) {
+                Ok(variant) => variants.push(variant),
+                Err(err) => report.skip("ZVARIANT", row.get(0)?, row, err)?,
+            }
+        }
+
+        Ok(variants)
+    }
+}
@@ -0,0 +1,136 @@
+/*
+ This Source Code Form is subject to the terms of the Mozilla Public
+ License, v. 2.0. If a copy of the MPL was not distributed with this
+ file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use std::collections::BTreeMap;
+
+use rusqlite::types::Value;
+
+use super::schema::{ExtraColumns, Query, Schema};
+use super::{CoId, LoadReport, Result};
+
+/// The user editable metadata of a variant, from ZVARIANTMETADATA.
+/// Mostly the IPTC Core and Extension fields.
+#[derive(Debug, Default)]
+pub struct VariantMetadata {
+    pub id: CoId,
+    /// The variant it belongs to.
+    pub variant: CoId,
+    pub title: Option<String>,
+    pub headline: Option<String>,
+    /// Caption.
+    pub description: Option<String>,
+    pub description_writer: Option<String>,
+    pub creator: Option<String>,
+    pub creator_job_title: Option<String>,
+    pub creator_address: Option<String>,
+    pub creator_city: Option<String>,
+    pub creator_state: Option<String>,
+    pub creator_postal_code: Option<String>,
+    pub creator_country: Option<String>,
+    pub creator_phone: Option<String>,
+    pub creator_email: Option<String>,
+    pub creator_url: Option<String>,
+    /// Copyright notice.
+    pub copyright: Option<String>,
+    pub usage_terms: Option<String>,
+    /// Credit line.
+    pub credit: Option<String>,
+    pub source: Option<String>,
+    pub instructions: Option<String>,
+    pub job_identifier: Option<String>,
+    pub intellectual_genre: Option<String>,
+    /// IPTC scene codes.
+    pub scene: Option<String>,
+    /// IPTC subject codes.
+    pub subject_code: Option<String>,
+    /// As entered, not parsed.
+    pub date_created: Option<String>,
+    pub sublocation: Option<String>,
+    pub city: Option<String>,
+    pub province_state: Option<String>,
+    pub country: Option<String>,
+    /// ISO country code.
+    pub country_code: Option<String>,
+    /// IPTC Extension.
+    pub person_in_image: Option<String>,
+    /// IPTC Extension.
+    pub event: Option<String>,
+    /// IPTC Extension.
+    pub organisation_name: Option<String>,
+    /// IPTC Extension.
+    pub organisation_code: Option<String>,
+    /// The other columns that are not `NULL`, by column name.
+    pub other: BTreeMap<String, Value>,
+}
+
+impl VariantMetadata {
+    fn from_row(row: &rusqlite::Row, extra: &ExtraColumns) -> rusqlite::Result<VariantMetadata> {
+        let mut other = BTreeMap::new();
+        for (idx, name) in extra.names.iter().enumerate() {
+            let value: Value = row.get(extra.first + idx)?;
+            if value != Value::Null {
+                other.insert(name.clone(), value);
+            }
+        }
+        Ok(VariantMetadata {
+            id: row.get(0)?,
+            variant: row.get(1)?,
+            title: row.get(2)?,
+            headline: row.get(3)?,
+            description: row.get(4)?,
+            description_writer: row.get(5)?,
+            creator: row.get(6)?,
+            creator_job_title: row.get(7)?,
+            creator_address: row.get(8)?,
+            creator_city: row.get(9)?,
+            creator_state: row.get(10)?,
+            creator_postal_code: row.get(11)?,
+            creator_country: row.get(12)?,
+            creator_phone: row.get(13)?,
+            creator_email: row.get(14)?,
+            creator_url: row.get(15)?,
+            copyright: row.get(16)?,
+            usage_terms: row.get(17)?,
+            credit: row.get(18)?,
+            source: row.get(19)?,
+            instructions: row.get(20)?,
+            job_identifier: row.get(21)?,
+            intellectual_genre: row.get(22)?,
+            scene: row.get(23)?,
+            subject_code: row.get(24)?,
+            date_created: row.get(25)?,
+            sublocation: row.get(26)?,
+            city: row.get(27)?,
+            province_state: row.get(28)?,
+            country: row.get(29)?,
+            country_code: row.get(30)?,
+            person_in_image: row.get(31)?,
+            event: row.get(32)?,
+            organisation_name: row.get(33)?,
+            organisation_code: row.get(34)?,
+            other,
+        })
+    }
+
+    pub(crate) fn load_objects(
+        conn: &rusqlite::Connection,
+        schema: &Schema,
+        report: &mut LoadReport,
+    ) -> Result<Vec<VariantMetadata>> {
+        let mut metadata: Vec<VariantMetadata> = vec![];
+        let (sql, extra) = schema.query_with_extra(Query::VariantMetadata)?;
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            match VariantMetadata::from_row(row, &extra) {
+                Ok(m) => metadata.push(m),
+                Err(err) => report.skip("ZVARIANTMETADATA", row.get(0)?, row, err)?,
+            }
+        }
+
+        Ok(metadata)
+    }
+}